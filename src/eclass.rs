@@ -6,6 +6,7 @@ use crate::{Id, Language};
 /// An equivalence class of enodes.
 #[non_exhaustive]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub struct EClass<L, D> {
     /// This eclass's id.
     pub id: Id,
@@ -14,6 +15,9 @@ pub struct EClass<L, D> {
     /// The analysis data associated with this eclass.
     pub data: D,
     pub(crate) parents: Vec<(L, Id)>,
+    /// The [`EGraph::timestamp`](crate::EGraph::timestamp()) of the
+    /// last change to this eclass.
+    pub(crate) modified: usize,
 }
 
 impl<L, D> EClass<L, D> {
@@ -34,6 +34,19 @@ You can use the `egraph[id]` syntax to get an [`EClass`] from an [`Id`], because
 and
 [`IndexMut`](struct.EGraph.html#impl-IndexMut<Id>).
 
+# Serialization
+
+If the `serde-1` feature is enabled, [`EGraph`] implements
+`Serialize` (when your [`Language`] and the analysis data do) and
+`Deserialize` (when they do and your [`Analysis`] implements
+`Default`), so a saturated e-graph can be saved and loaded later.
+The [`Analysis`] value itself is not saved; a deserialized [`EGraph`]
+starts with `N::default()`. Explanations are not saved either, so they
+are disabled after deserializing.
+Deserializing fails if the ids in the input are inconsistent, and
+otherwise [rebuilds](EGraph::rebuild()) the loaded [`EGraph`], so a
+hashcons that doesn't match the eclasses is repaired.
+
 [`add`]: EGraph::add()
 [`union`]: EGraph::union()
 [`rebuild`]: EGraph::rebuild()
@@ -44,15 +57,155 @@ and
 [sound]: https://itinerarium.github.io/phoneme-synthesis/?w=/'igraf/
 **/
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde-1",
+    derive(serde::Serialize),
+    serde(bound(serialize = "L: serde::Serialize, N::Data: serde::Serialize"))
+)]
 pub struct EGraph<L: Language, N: Analysis<L>> {
     /// The `Analysis` given when creating this `EGraph`.
+    #[cfg_attr(feature = "serde-1", serde(skip))]
     pub analysis: N,
     pending: Vec<(L, Id)>,
     analysis_pending: IndexSet<(L, Id)>,
+    #[cfg_attr(feature = "serde-1", serde(with = "crate::util::vectorize"))]
     memo: HashMap<L, Id>,
     unionfind: UnionFind,
+    #[cfg_attr(feature = "serde-1", serde(with = "crate::util::vectorize"))]
     classes: HashMap<Id, EClass<L, N::Data>>,
+    #[cfg_attr(feature = "serde-1", serde(skip))]
     pub(crate) classes_by_op: HashMap<std::mem::Discriminant<L>, HashSet<Id>>,
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    explain: Option<Explain<L>>,
+    clock: usize,
+    deterministic: bool,
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    undo: Vec<Checkpoint<L, N::Data>>,
+    // set by the `Runner` while searching, so searches can end early
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    stop: Option<StopHandle>,
+    // searches eclasses in parallel, see `with_parallel_search`
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(feature = "serde-1", serde(skip))]
+    pub(crate) par_search: Option<pattern::ParSearch<L, N>>,
+}
+
+// what is needed to roll an egraph back to a `push`, see `EGraph::pop`
+#[derive(Clone)]
+struct Checkpoint<L, D> {
+    // the number of ids at the push, later ids are removed on pop
+    n_ids: usize,
+    // eclasses that existed at the push, saved as they were before
+    // they first changed
+    classes: HashMap<Id, EClass<L, D>>,
+    // roots at the push that have been unioned into another eclass
+    unions: Vec<Id>,
+    // hashcons entries as they were before each change, oldest first
+    memo: Vec<(L, Option<Id>)>,
+    // `Clone::clone` for the analysis data, which only needs to be
+    // `Clone` for `push`
+    clone_data: fn(&D) -> D,
+}
+
+// the serialized parts of an EGraph, see the Deserialize impl below
+#[cfg(feature = "serde-1")]
+#[derive(serde::Deserialize)]
+#[serde(bound(deserialize = "L: Language + serde::Deserialize<'de>, D: serde::Deserialize<'de>"))]
+struct EGraphParts<L, D> {
+    pending: Vec<(L, Id)>,
+    analysis_pending: IndexSet<(L, Id)>,
+    #[serde(with = "crate::util::vectorize")]
+    memo: HashMap<L, Id>,
+    unionfind: UnionFind,
+    #[serde(with = "crate::util::vectorize")]
+    classes: HashMap<Id, EClass<L, D>>,
+    clock: usize,
+    deterministic: bool,
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de, L, N> serde::Deserialize<'de> for EGraph<L, N>
+where
+    L: Language + serde::Deserialize<'de>,
+    N: Analysis<L> + Default,
+    N::Data: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let parts = EGraphParts::<L, N::Data>::deserialize(deserializer)?;
+        parts.check().map_err(serde::de::Error::custom)?;
+        let mut egraph = Self {
+            analysis: N::default(),
+            pending: parts.pending,
+            analysis_pending: parts.analysis_pending,
+            memo: parts.memo,
+            unionfind: parts.unionfind,
+            classes: parts.classes,
+            classes_by_op: Default::default(),
+            explain: None,
+            clock: parts.clock,
+            deterministic: parts.deterministic,
+            undo: vec![],
+            stop: None,
+            #[cfg(feature = "parallel")]
+            par_search: None,
+        };
+        // make the hashcons and parents agree with the eclasses instead
+        // of trusting the input
+        egraph.repair_memo();
+        egraph.rebuild();
+        Ok(egraph)
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<L: Language, D> EGraphParts<L, D> {
+    // checks that every id is in bounds and that the eclasses are
+    // exactly the roots of the union-find, so loading can't panic
+    fn check(&self) -> Result<(), String> {
+        let n_ids = self.unionfind.size();
+        let check_id = |id: Id| {
+            if usize::from(id) < n_ids {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Id {} is out of bounds, there are {} ids",
+                    id, n_ids
+                ))
+            }
+        };
+        let check_node = |node: &L| node.try_for_each(check_id);
+
+        self.unionfind.check()?;
+        for (&id, class) in &self.classes {
+            check_id(id)?;
+            if class.id != id {
+                return Err(format!("Eclass {} is stored under id {}", class.id, id));
+            }
+            if self.unionfind.find(id) != id {
+                return Err(format!("Eclass {} is not canonical", id));
+            }
+            class.nodes.iter().try_for_each(check_node)?;
+            for (node, parent) in &class.parents {
+                check_node(node)?;
+                check_id(*parent)?;
+            }
+        }
+        for id in (0..n_ids).map(Id::from) {
+            if self.unionfind.find(id) == id && !self.classes.contains_key(&id) {
+                return Err(format!("Missing eclass {}", id));
+            }
+        }
+        let memo = self.memo.iter().map(|(node, &id)| (node, id));
+        let pending = self.pending.iter().chain(&self.analysis_pending);
+        for (node, id) in memo.chain(pending.map(|(node, id)| (node, *id))) {
+            check_node(node)?;
+            check_id(id)?;
+        }
+        Ok(())
+    }
 }
 
 impl<L: Language, N: Analysis<L> + Default> Default for EGraph<L, N> {
@@ -82,7 +235,170 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
             pending: Default::default(),
             analysis_pending: Default::default(),
             classes_by_op: Default::default(),
+            explain: None,
+            clock: 0,
+            deterministic: false,
+            undo: vec![],
+            stop: None,
+            #[cfg(feature = "parallel")]
+            par_search: None,
+        }
+    }
+
+    /// Enables explanations for this `EGraph`.
+    ///
+    /// With explanations enabled, the `EGraph` remembers why every
+    /// union happened, so that [`explain_equivalence`] can later
+    /// produce a step-by-step proof of why two terms are equal.
+    /// This costs some extra memory and time per added e-node.
+    ///
+    /// Panics if the `EGraph` is not empty.
+    ///
+    /// [`explain_equivalence`]: EGraph::explain_equivalence()
+    pub fn with_explanations_enabled(mut self) -> Self {
+        if !self.is_empty() {
+            panic!("Explanations must be enabled before adding anything to the egraph");
+        }
+        self.explain = Some(Explain::new());
+        self
+    }
+
+    /// Returns `true` if explanations are enabled.
+    pub fn are_explanations_enabled(&self) -> bool {
+        self.explain.is_some()
+    }
+
+    /// Makes this `EGraph` go through eclasses in order of their ids
+    /// where the order matters, instead of in hash table order.
+    ///
+    /// Hash table order depends on the hasher and on the history of the
+    /// table, so it can change with the platform, the Rust version, or
+    /// unrelated changes to how the egraph was built.
+    /// With a deterministic order, searching ([`Pattern`]s and
+    /// [`MultiPattern`]s) returns matches sorted by eclass, so rules
+    /// apply and union in the same order, and the [`Extractor`] breaks
+    /// ties the same way.
+    /// As a result, running the same rules on the same terms gives the
+    /// same [`Id`]s, extracted terms and explanations every time.
+    /// This costs some sorting on every search and extraction.
+    ///
+    /// [`classes`](EGraph::classes()) still iterates in hash table
+    /// order.
+    pub fn with_deterministic_order(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Makes [`Pattern`]s and [`MultiPattern`]s search their candidate
+    /// eclasses concurrently on the current
+    /// [rayon](https://docs.rs/rayon) thread pool.
+    ///
+    /// The matches are collected in the same order as a sequential
+    /// search, so everything else about a [`Runner`] is unaffected.
+    /// This needs the `parallel` feature, and the language, analysis and
+    /// analysis data have to be shareable across threads.
+    /// To limit the number of threads, run the search inside your own
+    /// `rayon::ThreadPool`.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_search(mut self) -> Self
+    where
+        L: Send + Sync,
+        N: Sync,
+        N::Data: Sync,
+    {
+        self.par_search = Some(pattern::par_search);
+        self
+    }
+
+    /// Returns `true` if the egraph goes through eclasses in a
+    /// deterministic order, see
+    /// [`with_deterministic_order`](EGraph::with_deterministic_order()).
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    pub(crate) fn set_stop_handle(&mut self, stop: Option<StopHandle>) {
+        self.stop = stop;
+    }
+
+    // whether searches should give up on the eclasses they haven't
+    // searched yet, see `set_stop_handle`
+    pub(crate) fn is_search_stopped(&self) -> bool {
+        self.stop.as_ref().map_or(false, |stop| stop.is_stopped())
+    }
+
+    // sorts the given ids if the egraph is deterministic
+    pub(crate) fn order_ids(&self, ids: &mut [Id]) {
+        if self.deterministic {
+            ids.sort_unstable();
+        }
+    }
+
+    /// Returns the current timestamp of the egraph.
+    ///
+    /// The timestamp advances whenever an eclass changes in a way that
+    /// may create new matches: it is created, unioned with another, or
+    /// its analysis data changes during [`rebuild`](EGraph::rebuild()).
+    /// Changes made directly through [`IndexMut`](std::ops::IndexMut)
+    /// are not tracked.
+    /// Pass it to [`classes_touched_since`](EGraph::classes_touched_since())
+    /// or [`Searcher::search_since`] later to only look at what changed.
+    /// A fresh egraph has timestamp 0.
+    pub fn timestamp(&self) -> usize {
+        self.clock
+    }
+
+    /// Returns the eclasses that changed after the given
+    /// [`timestamp`](EGraph::timestamp()), together with their ancestors
+    /// up to `depth` parent steps away, sorted by id.
+    ///
+    /// A match of a pattern with `depth + 1` levels that wasn't there at
+    /// `timestamp` must be rooted in one of these eclasses, since some
+    /// eclass it touches must have changed.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let fx = egraph.add_expr(&"(f x)".parse().unwrap());
+    /// let gy = egraph.add_expr(&"(g (h y))".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let timestamp = egraph.timestamp();
+    /// let x = egraph.add(S::leaf("x"));
+    /// let z = egraph.add(S::leaf("z"));
+    /// egraph.union(x, z);
+    /// egraph.rebuild();
+    ///
+    /// let x = egraph.find(x);
+    /// assert_eq!(egraph.classes_touched_since(timestamp, 0), vec![x]);
+    /// assert_eq!(egraph.classes_touched_since(timestamp, 1), vec![x, fx]);
+    /// assert!(!egraph.classes_touched_since(timestamp, 5).contains(&gy));
+    /// ```
+    pub fn classes_touched_since(&self, timestamp: usize, depth: usize) -> Vec<Id> {
+        let mut touched: HashSet<Id> = self
+            .classes()
+            .filter(|class| class.modified > timestamp)
+            .map(|class| class.id)
+            .collect();
+
+        let mut frontier: Vec<Id> = touched.iter().copied().collect();
+        for _ in 0..depth {
+            let mut next = vec![];
+            for id in frontier {
+                for (_, parent) in &self[id].parents {
+                    let parent = self.find(*parent);
+                    if touched.insert(parent) {
+                        next.push(parent);
+                    }
+                }
+            }
+            frontier = next;
         }
+
+        let mut touched: Vec<Id> = touched.into_iter().collect();
+        touched.sort_unstable();
+        touched
     }
 
     /// Returns an iterator over the eclasses in the egraph.
@@ -91,7 +407,15 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     }
 
     /// Returns an mutating iterator over the eclasses in the egraph.
+    ///
+    /// If there is a checkpoint (see [`push`](EGraph::push())), this
+    /// first saves every eclass so it can be restored, which is
+    /// expensive. Prefer [`IndexMut`](std::ops::IndexMut) then.
     pub fn classes_mut(&mut self) -> impl ExactSizeIterator<Item = &mut EClass<L, N::Data>> {
+        if !self.undo.is_empty() {
+            let ids: Vec<Id> = self.classes.keys().copied().collect();
+            ids.into_iter().for_each(|id| self.save_class(id));
+        }
         self.classes.values_mut()
     }
 
@@ -138,6 +462,54 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         self.classes.len()
     }
 
+    /// Estimates how many bytes of memory the egraph uses.
+    ///
+    /// This counts the eclasses with their enodes, parent lists and
+    /// analysis data (including heap memory reported by
+    /// [`Analysis::data_heap_size`]), the hashcons, the union-find,
+    /// and the index of eclasses by operator.
+    /// It doesn't count heap memory owned by the enodes themselves
+    /// (like the children of a [`SymbolLang`]), explanations, or
+    /// allocator overhead, so it is a lower bound.
+    /// This takes time linear in the number of eclasses.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let empty = egraph.estimated_memory();
+    /// egraph.add_expr(&"(+ a (* b c))".parse().unwrap());
+    /// assert!(egraph.estimated_memory() > empty);
+    /// ```
+    pub fn estimated_memory(&self) -> usize {
+        use std::mem::size_of;
+
+        let classes: usize = self
+            .classes
+            .values()
+            .map(|class| {
+                class.nodes.capacity() * size_of::<L>()
+                    + class.parents.capacity() * size_of::<(L, Id)>()
+                    + self.analysis.data_heap_size(&class.data)
+            })
+            .sum();
+        let by_op: usize = self
+            .classes_by_op
+            .values()
+            .map(|ids| ids.capacity() * size_of::<Id>())
+            .sum();
+
+        size_of::<Self>()
+            + classes
+            + self.classes.capacity() * size_of::<(Id, EClass<L, N::Data>)>()
+            + self.memo.capacity() * size_of::<(L, Id)>()
+            + self.unionfind.size() * size_of::<Id>()
+            + self.classes_by_op.capacity() * size_of::<(std::mem::Discriminant<L>, HashSet<Id>)>()
+            + by_op
+            + self.pending.capacity() * size_of::<(L, Id)>()
+            + self.analysis_pending.capacity() * size_of::<(L, Id)>()
+    }
+
     /// Canonicalizes an eclass id.
     ///
     /// This corresponds to the `find` operation on the egraph's
@@ -160,8 +532,14 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
     /// This is private, but internals should use this whenever
     /// possible because it does path compression.
+    /// There is no path compression while there is a checkpoint, since
+    /// [`pop`](EGraph::pop()) only undoes the unions themselves.
     fn find_mut(&mut self, id: Id) -> Id {
-        self.unionfind.find_mut(id)
+        if self.undo.is_empty() {
+            self.unionfind.find_mut(id)
+        } else {
+            self.unionfind.find(id)
+        }
     }
 
     /// Creates a [`Dot`] to visualize this egraph. See [`Dot`].
@@ -187,6 +565,7 @@ impl<L: Language, N: Analysis<L>> std::ops::Index<Id> for EGraph<L, N> {
 impl<L: Language, N: Analysis<L>> std::ops::IndexMut<Id> for EGraph<L, N> {
     fn index_mut(&mut self, id: Id) -> &mut Self::Output {
         let id = self.find_mut(id);
+        self.save_class(id);
         self.classes
             .get_mut(&id)
             .unwrap_or_else(|| panic!("Invalid id {}", id))
@@ -209,15 +588,29 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     ///
     /// [`add_expr`]: EGraph::add_expr()
     pub fn add_expr(&mut self, expr: &RecExpr<L>) -> Id {
+        let id = self.add_expr_uncanonical(expr);
+        self.find(id)
+    }
+
+    /// Like [`add_expr`](EGraph::add_expr()), but returns the id of
+    /// exactly the given term when explanations are enabled.
+    fn add_expr_uncanonical(&mut self, expr: &RecExpr<L>) -> Id {
         let nodes = expr.as_ref();
         let mut new_ids = Vec::with_capacity(nodes.len());
         for node in nodes {
             let node = node.clone().map_children(|i| new_ids[usize::from(i)]);
-            new_ids.push(self.add(node))
+            new_ids.push(self.add_uncanonical(node))
         }
         *new_ids.last().unwrap()
     }
 
+    /// Adds the instantiation of a pattern with the given substitution.
+    pub(crate) fn add_instantiation(&mut self, pat: &PatternAst<L>, subst: &Subst) -> Id {
+        let nodes = pat.as_ref();
+        let mut ids = vec![0.into(); nodes.len()];
+        pattern::apply_pat(&mut ids, nodes, self, subst)
+    }
+
     /// Lookup the eclass of the given enode.
     ///
     /// You can pass in either an owned enode or a `&mut` enode,
@@ -243,14 +636,21 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     /// assert_eq!(egraph.lookup(&mut node_f_ab), Some(id));
     /// assert_eq!(node_f_ab, SymbolLang::new("f", vec![a, a]));
     /// ```
-    pub fn lookup<B>(&self, mut enode: B) -> Option<Id>
+    pub fn lookup<B>(&self, enode: B) -> Option<Id>
+    where
+        B: BorrowMut<L>,
+    {
+        self.lookup_internal(enode).map(|id| self.find(id))
+    }
+
+    // returns the id stored in the memo, which need not be canonical
+    fn lookup_internal<B>(&self, mut enode: B) -> Option<Id>
     where
         B: BorrowMut<L>,
     {
         let enode = enode.borrow_mut();
         enode.update_children(|id| self.find(id));
-        let id = self.memo.get(enode);
-        id.map(|&id| self.find(id))
+        self.memo.get(enode).copied()
     }
 
     /// Adds an enode to the [`EGraph`].
@@ -264,32 +664,72 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     /// Otherwise
     ///
     /// [`add`]: EGraph::add()
-    pub fn add(&mut self, mut enode: L) -> Id {
-        self.lookup(&mut enode).unwrap_or_else(|| {
-            let id = self.unionfind.make_set();
-            log::trace!("  ...adding to {}", id);
-            let class = EClass {
-                id,
-                nodes: vec![enode.clone()],
-                data: N::make(self, &enode),
-                parents: Default::default(),
-            };
-
-            // add this enode to the parent lists of its children
-            enode.for_each(|child| {
-                let tup = (enode.clone(), id);
-                self[child].parents.push(tup);
-            });
-
-            // TODO is this needed?
-            self.pending.push((enode.clone(), id));
+    pub fn add(&mut self, enode: L) -> Id {
+        let id = self.add_uncanonical(enode);
+        self.find(id)
+    }
 
-            self.classes.insert(id, class);
-            assert!(self.memo.insert(enode, id).is_none());
+    /// Adds an enode, returning an id for exactly this enode.
+    ///
+    /// Without explanations this is just [`add`](EGraph::add()).
+    /// With explanations, an enode whose children are equivalent to (but
+    /// not the same as) an existing enode gets its own id, which is
+    /// unioned with the existing one by congruence. That way every id
+    /// stands for one concrete term.
+    pub(crate) fn add_uncanonical(&mut self, mut enode: L) -> Id {
+        if let Some(explain) = &self.explain {
+            if let Some(&id) = explain.uncanon_memo.get(&enode) {
+                return id;
+            }
+        }
 
+        let original = self.explain.as_ref().map(|_| enode.clone());
+        if let Some(existing_id) = self.lookup_internal(&mut enode) {
+            let id = self.find(existing_id);
+            match (self.explain.as_mut(), original) {
+                (Some(explain), Some(original)) => {
+                    let new_id = self.unionfind.make_set();
+                    explain.add(original, new_id);
+                    self.unionfind.union(id, new_id);
+                    explain.union(existing_id, new_id, Justification::Congruence);
+                    new_id
+                }
+                _ => id,
+            }
+        } else {
+            let id = self.make_new_eclass(enode);
+            if let Some(original) = original {
+                self.explain.as_mut().unwrap().add(original, id);
+            }
             N::modify(self, id);
             id
-        })
+        }
+    }
+
+    fn make_new_eclass(&mut self, enode: L) -> Id {
+        let id = self.unionfind.make_set();
+        log::trace!("  ...adding to {}", id);
+        self.clock += 1;
+        let class = EClass {
+            id,
+            nodes: vec![enode.clone()],
+            data: N::make(self, &enode),
+            parents: Default::default(),
+            modified: self.clock,
+        };
+
+        // add this enode to the parent lists of its children
+        enode.for_each(|child| {
+            let tup = (enode.clone(), id);
+            self[child].parents.push(tup);
+        });
+
+        // TODO is this needed?
+        self.pending.push((enode.clone(), id));
+
+        self.classes.insert(id, class);
+        assert!(self.memo_insert(enode, id).is_none());
+        id
     }
 
     /// Checks whether two [`RecExpr`]s are equivalent.
@@ -344,14 +784,113 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
     /// The returned `bool` indicates whether a union was done,
     /// so it's `false` if they were already equivalent.
     /// Both results are canonical.
-    pub fn union(&mut self, mut id1: Id, mut id2: Id) -> (Id, bool) {
-        id1 = self.find_mut(id1);
-        id2 = self.find_mut(id2);
+    ///
+    /// When explanations are enabled, the union is justified as
+    /// [`Justification::Trusted`] with the reason `"union"`; use
+    /// [`union_trusted`](EGraph::union_trusted()) to give a better one.
+    pub fn union(&mut self, id1: Id, id2: Id) -> (Id, bool) {
+        let justification = self
+            .explain
+            .as_ref()
+            .map(|_| Justification::Trusted("union".into()));
+        self.perform_union(id1, id2, justification)
+    }
+
+    /// Unions two eclasses, recording `reason` as the justification
+    /// when explanations are enabled.
+    ///
+    /// Otherwise this is the same as [`union`](EGraph::union()).
+    pub fn union_trusted(&mut self, from: Id, to: Id, reason: impl Into<Symbol>) -> (Id, bool) {
+        self.perform_union(from, to, Some(Justification::Trusted(reason.into())))
+    }
+
+    /// Adds the instantiations of two patterns under `subst` and unions
+    /// them, recording `rule_name` and `subst` as the justification
+    /// when explanations are enabled.
+    ///
+    /// Returns the same as [`union`](EGraph::union()).
+    pub fn union_instantiations(
+        &mut self,
+        from_pat: &PatternAst<L>,
+        to_pat: &PatternAst<L>,
+        subst: &Subst,
+        rule_name: impl Into<Symbol>,
+    ) -> (Id, bool) {
+        let from = self.add_instantiation(from_pat, subst);
+        let to = self.add_instantiation(to_pat, subst);
+        let justification = Justification::Rule {
+            name: rule_name.into(),
+            subst: subst.clone(),
+        };
+        self.perform_union(from, to, Some(justification))
+    }
+
+    /// Unions the result `id` of applying a rule with the eclass where
+    /// the rule matched. [`Applier`]s should go through this so the
+    /// union can be explained by the rule being applied, see
+    /// [`Rewrite::apply`]. If `matched_term` is true, the proof starts
+    /// from the term the searcher's pattern matched instead of `eclass`.
+    pub(crate) fn union_match(
+        &mut self,
+        eclass: Id,
+        id: Id,
+        subst: &Subst,
+        matched_term: bool,
+    ) -> (Id, bool) {
+        let rule = match self.explain.as_mut() {
+            None => return self.perform_union(id, eclass, None),
+            Some(explain) => explain.rule.take(),
+        };
+        let rule = match rule {
+            Some(rule) => rule,
+            // not applied through a rewrite
+            None => return self.union(id, eclass),
+        };
+
+        // the proof needs the exact term that matched, not just its eclass
+        let from = match &rule.searcher_ast {
+            Some(ast) if matched_term => self.add_instantiation(ast, subst),
+            _ => eclass,
+        };
+        let justification = Justification::Rule {
+            name: rule.name,
+            subst: subst.clone(),
+        };
+        self.explain.as_mut().unwrap().rule = Some(rule);
+        self.perform_union(from, id, Some(justification))
+    }
+
+    // sets the rewrite whose unions `union_match` justifies, returning
+    // the previous one
+    pub(crate) fn set_rule_context(
+        &mut self,
+        rule: Option<RuleContext<L>>,
+    ) -> Option<RuleContext<L>> {
+        match self.explain.as_mut() {
+            Some(explain) => std::mem::replace(&mut explain.rule, rule),
+            None => None,
+        }
+    }
+
+    // `justification` must be `Some` if explanations are enabled
+    fn perform_union(
+        &mut self,
+        enode_id1: Id,
+        enode_id2: Id,
+        justification: Option<Justification>,
+    ) -> (Id, bool) {
+        let mut id1 = self.find_mut(enode_id1);
+        let mut id2 = self.find_mut(enode_id2);
 
         if id1 == id2 {
             return (id1, false);
         }
 
+        if let Some(explain) = self.explain.as_mut() {
+            let justification = justification.expect("Missing justification for union");
+            explain.union(enode_id1, enode_id2, justification);
+        }
+
         // make sure class2 has fewer parents
         let class1_parents = self.classes[&id1].parents.len();
         let class2_parents = self.classes[&id2].parents.len();
@@ -361,6 +900,12 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
         N::pre_union(self, id1, id2);
 
+        self.save_class(id1);
+        self.save_class(id2);
+        if let Some(checkpoint) = self.undo.last_mut() {
+            checkpoint.unions.push(id2);
+        }
+
         // make id1 the new root
         self.unionfind.union(id1, id2);
 
@@ -368,6 +913,8 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         let class2 = self.classes.remove(&id2).unwrap();
         let class1 = self.classes.get_mut(&id1).unwrap();
         assert_eq!(id1, class1.id);
+        self.clock += 1;
+        class1.modified = self.clock;
 
         self.pending.extend(class2.parents.iter().cloned());
         match self.analysis.merge(&mut class1.data, class2.data) {
@@ -391,6 +938,450 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         (id1, id1 != id2)
     }
 
+    /// Removes an enode from the given eclass.
+    ///
+    /// The enode is compared by its canonical form, so it doesn't matter
+    /// whether its children are canonical.
+    /// Besides the eclass itself, this also removes the enode from the
+    /// hashcons, from the parent lists of its children, and from the
+    /// index used to find eclasses by operator, so the egraph stays
+    /// valid without needing a [`rebuild`](EGraph::rebuild()).
+    /// Returns `true` if the enode was in the eclass.
+    ///
+    /// The eclass's analysis data is left untouched.
+    /// Explanations still refer to a removed enode if it took part in
+    /// a union, so [`explain_equivalence`](EGraph::explain_equivalence())
+    /// may mention it.
+    /// Adding the enode again puts it in a new eclass.
+    ///
+    /// Panics if this would remove the last enode of the eclass.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let x = egraph.add(S::leaf("x"));
+    /// let y = egraph.add(S::leaf("y"));
+    /// let plus = egraph.add(S::new("+", vec![x, y]));
+    /// egraph.union(x, plus);
+    /// egraph.rebuild();
+    ///
+    /// assert!(egraph.remove_node(x, &S::new("+", vec![x, y])));
+    /// assert_eq!(egraph.lookup(S::new("+", vec![x, y])), None);
+    /// assert_eq!(egraph[x].len(), 1);
+    /// ```
+    pub fn remove_node(&mut self, id: Id, enode: &L) -> bool {
+        let enode = enode.clone().map_children(|c| self.find(c));
+        self.remove_nodes_where(id, |n| n == &enode) > 0
+    }
+
+    /// Removes every enode of the given eclass for which `keep` returns
+    /// `false`, returning how many were removed.
+    ///
+    /// `keep` is called with canonical enodes.
+    /// This is useful in [`Analysis::modify`] to prune enodes that are
+    /// dominated by others, for example everything but the constant in a
+    /// constant-folded eclass.
+    /// Like [`remove_node`](EGraph::remove_node()), this keeps the
+    /// hashcons, parent lists, and operator index consistent.
+    ///
+    /// Panics if this would remove every enode of the eclass.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let one = egraph.add(S::leaf("1"));
+    /// let sum = egraph.add_expr(&"(+ 0 1)".parse().unwrap());
+    /// egraph.union(sum, one);
+    /// egraph.rebuild();
+    ///
+    /// assert_eq!(egraph.prune_class(sum, |n| n.is_leaf()), 1);
+    /// assert_eq!(egraph[sum].nodes, vec![S::leaf("1")]);
+    /// ```
+    pub fn prune_class(&mut self, id: Id, mut keep: impl FnMut(&L) -> bool) -> usize {
+        self.remove_nodes_where(id, |n| !keep(n))
+    }
+
+    fn remove_nodes_where(&mut self, id: Id, mut remove: impl FnMut(&L) -> bool) -> usize {
+        let id = self.find_mut(id);
+        let nodes = std::mem::take(&mut self[id].nodes);
+        let mut kept = Vec::with_capacity(nodes.len());
+        let mut removed = vec![];
+        for node in nodes {
+            let canonical = node.clone().map_children(|c| self.find(c));
+            if remove(&canonical) {
+                removed.push(canonical);
+            } else {
+                kept.push(node);
+            }
+        }
+
+        if removed.is_empty() {
+            self[id].nodes = kept;
+            return 0;
+        }
+        assert!(
+            !kept.is_empty(),
+            "Cannot remove every enode of eclass {}",
+            id
+        );
+
+        removed.sort_unstable();
+        removed.dedup();
+        let n_removed = removed.len();
+
+        for node in &removed {
+            node.for_each(|child| {
+                let child = self.find(child);
+                self.save_class(child);
+            });
+        }
+
+        let uf = &self.unionfind;
+        let is_removed = |node: &L, class: Id| {
+            uf.find(class) == id && {
+                let canonical = node.clone().map_children(|c| uf.find(c));
+                removed.binary_search(&canonical).is_ok()
+            }
+        };
+
+        for node in &removed {
+            if self.memo.get(node).map(|&c| uf.find(c)) == Some(id) {
+                let old = self.memo.remove(node);
+                if let Some(checkpoint) = self.undo.last_mut() {
+                    checkpoint.memo.push((node.clone(), old));
+                }
+            }
+            let classes = &mut self.classes;
+            node.for_each(|child| {
+                let parents = &mut classes.get_mut(&uf.find(child)).unwrap().parents;
+                parents.retain(|(p, class)| !is_removed(p, *class));
+            });
+            #[allow(clippy::mem_discriminant_non_enum)]
+            let discrim = std::mem::discriminant(node);
+            #[allow(clippy::mem_discriminant_non_enum)]
+            let still_has_op = kept.iter().any(|n| std::mem::discriminant(n) == discrim);
+            if !still_has_op {
+                if let Some(ids) = self.classes_by_op.get_mut(&discrim) {
+                    ids.remove(&id);
+                }
+            }
+        }
+        self.pending.retain(|(n, class)| !is_removed(n, *class));
+        self.analysis_pending
+            .retain(|(n, class)| !is_removed(n, *class));
+        if let Some(explain) = self.explain.as_mut() {
+            // otherwise adding a removed enode again would return its old id
+            explain
+                .uncanon_memo
+                .retain(|n, class| !is_removed(n, *class));
+        }
+
+        self.classes.get_mut(&id).unwrap().nodes = kept;
+        n_removed
+    }
+
+    /// Removes every eclass that is not reachable from the given roots,
+    /// and renumbers the remaining eclasses densely from zero.
+    ///
+    /// This keeps memory bounded when an [`EGraph`] is reused across
+    /// several rounds of rewriting, but only some terms are still of
+    /// interest.
+    /// The egraph is [rebuilt](EGraph::rebuild()) first.
+    /// Afterwards, every [`Id`] in the egraph is new, so this returns a
+    /// map from the old ids (canonical or not) of the remaining eclasses
+    /// to their new ids. Old ids of removed eclasses are not in the map.
+    /// The analysis data of the remaining eclasses is kept as is, so
+    /// any [`Id`]s stored in it are not updated.
+    ///
+    /// Panics if explanations are enabled, since they refer to the old
+    /// ids.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let junk = egraph.add_expr(&"(g c d)".parse().unwrap());
+    /// let root = egraph.add_expr(&"(f a b)".parse().unwrap());
+    /// egraph.rebuild();
+    ///
+    /// let remap = egraph.retain_reachable(&[root]);
+    /// assert_eq!(egraph.number_of_classes(), 3);
+    /// assert!(!remap.contains_key(&junk));
+    /// assert_eq!(egraph.add_expr(&"(f a b)".parse().unwrap()), remap[&root]);
+    /// assert_eq!(egraph.number_of_classes(), 3);
+    /// ```
+    pub fn retain_reachable(&mut self, roots: &[Id]) -> HashMap<Id, Id> {
+        if self.explain.is_some() {
+            panic!("Cannot remove eclasses while explanations are enabled");
+        }
+        if !self.undo.is_empty() {
+            panic!("Cannot remove eclasses while there is a checkpoint");
+        }
+        self.rebuild();
+
+        let mut reachable: HashSet<Id> = HashSet::default();
+        let mut todo: Vec<Id> = roots.iter().map(|&id| self.find(id)).collect();
+        while let Some(id) = todo.pop() {
+            if reachable.insert(id) {
+                for node in &self.classes[&id].nodes {
+                    node.for_each(|child| todo.push(self.find(child)));
+                }
+            }
+        }
+
+        // give the remaining eclasses new ids in the order of the old ones
+        let mut kept: Vec<Id> = reachable.into_iter().collect();
+        kept.sort_unstable();
+        let new_ids: HashMap<Id, Id> = kept
+            .iter()
+            .enumerate()
+            .map(|(i, &old)| (old, Id::from(i)))
+            .collect();
+
+        let mut remap = HashMap::default();
+        for i in 0..self.unionfind.size() {
+            let old = Id::from(i);
+            if let Some(&new) = new_ids.get(&self.find(old)) {
+                remap.insert(old, new);
+            }
+        }
+
+        let mut old_classes = std::mem::take(&mut self.classes);
+        self.unionfind = UnionFind::default();
+        self.memo.clear();
+        for &old in &kept {
+            let mut class = old_classes.remove(&old).unwrap();
+            let id = self.unionfind.make_set();
+            debug_assert_eq!(id, new_ids[&old]);
+            class.id = id;
+            for node in &mut class.nodes {
+                node.update_children(|child| new_ids[&child]);
+                self.memo.insert(node.clone(), id);
+            }
+            // parents in removed eclasses are gone
+            class.parents = class
+                .parents
+                .into_iter()
+                .filter_map(|(parent, parent_class)| {
+                    let parent_class = *remap.get(&parent_class)?;
+                    let parent = parent.map_children(|child| remap[&child]);
+                    Some((parent, parent_class))
+                })
+                .collect();
+            self.classes.insert(id, class);
+        }
+
+        self.rebuild_classes();
+        debug_assert!(self.check_memo());
+        remap
+    }
+
+    /// Records a checkpoint that [`pop`](EGraph::pop()) can roll the
+    /// egraph back to.
+    ///
+    /// The egraph is [rebuilt](EGraph::rebuild()) first.
+    /// After a checkpoint, the egraph keeps an undo log of the enodes
+    /// and eclasses it adds, the unions it performs, and the eclasses
+    /// and analysis data they change, which is much cheaper than
+    /// [`clone`](Clone::clone())-ing the whole egraph.
+    /// Checkpoints nest: each `pop` undoes the latest `push`.
+    /// This is useful for case splits, where you add an assumption,
+    /// see what follows from it, and take it back.
+    ///
+    /// While there is a checkpoint, the egraph doesn't compress paths
+    /// in its union-find, and [`classes_mut`](EGraph::classes_mut())
+    /// has to save every eclass.
+    ///
+    /// Panics if explanations are enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let mut egraph = EGraph::<S, ()>::default();
+    /// let fa = egraph.add_expr(&"(f a)".parse().unwrap());
+    /// let fb = egraph.add_expr(&"(f b)".parse().unwrap());
+    /// let a = egraph.add(S::leaf("a"));
+    /// let b = egraph.add(S::leaf("b"));
+    /// egraph.rebuild();
+    ///
+    /// // assume a = b
+    /// egraph.push();
+    /// egraph.union(a, b);
+    /// egraph.add_expr(&"(g a)".parse().unwrap());
+    /// egraph.rebuild();
+    /// assert_eq!(egraph.find(fa), egraph.find(fb));
+    ///
+    /// // and take it back
+    /// egraph.pop();
+    /// assert_ne!(egraph.find(fa), egraph.find(fb));
+    /// assert_eq!(egraph.lookup(S::new("g", vec![a])), None);
+    /// assert_eq!(egraph.number_of_classes(), 4);
+    /// ```
+    pub fn push(&mut self)
+    where
+        N::Data: Clone,
+    {
+        if self.explain.is_some() {
+            panic!("Cannot push a checkpoint while explanations are enabled");
+        }
+        self.rebuild();
+        self.undo.push(Checkpoint {
+            n_ids: self.unionfind.size(),
+            classes: Default::default(),
+            unions: vec![],
+            memo: vec![],
+            clone_data: <N::Data as Clone>::clone,
+        });
+    }
+
+    /// Rolls the egraph back to the latest checkpoint from
+    /// [`push`](EGraph::push()), and forgets that checkpoint.
+    ///
+    /// Everything added since is removed and every union since is
+    /// undone, including any pending ones, so the egraph is as it was
+    /// right after the `push`, [rebuilt](EGraph::rebuild()) and with the
+    /// same [`Id`]s.
+    /// Only the [`timestamp`](EGraph::timestamp()) keeps advancing,
+    /// and the restored eclasses count as changed.
+    ///
+    /// Panics if there is no checkpoint.
+    pub fn pop(&mut self) {
+        let checkpoint = self.undo.pop().expect("No checkpoint to pop");
+        let n_ids = checkpoint.n_ids;
+
+        self.pending.clear();
+        self.analysis_pending.clear();
+
+        for (enode, old) in checkpoint.memo.into_iter().rev() {
+            match old {
+                Some(id) => self.memo.insert(enode, id),
+                None => self.memo.remove(&enode),
+            };
+        }
+
+        for root in checkpoint.unions.into_iter().rev() {
+            if usize::from(root) < n_ids {
+                self.unionfind.make_root(root);
+            }
+        }
+        for id in n_ids..self.unionfind.size() {
+            self.classes.remove(&Id::from(id));
+        }
+        self.unionfind.truncate(n_ids);
+
+        let restored = checkpoint.classes;
+        for ids in self.classes_by_op.values_mut() {
+            ids.retain(|id| usize::from(*id) < n_ids && !restored.contains_key(id));
+        }
+
+        self.clock += 1;
+        for (id, mut class) in restored {
+            class.modified = self.clock;
+            for node in &class.nodes {
+                #[allow(clippy::mem_discriminant_non_enum)]
+                self.classes_by_op
+                    .entry(std::mem::discriminant(node))
+                    .or_default()
+                    .insert(id);
+            }
+            self.classes.insert(id, class);
+        }
+
+        debug_assert!(self.check_memo());
+    }
+
+    /// Returns the number of checkpoints from [`push`](EGraph::push())
+    /// that haven't been [`pop`](EGraph::pop())ped yet.
+    pub fn checkpoints(&self) -> usize {
+        self.undo.len()
+    }
+
+    // saves the eclass for the latest checkpoint if it existed then and
+    // isn't saved yet
+    fn save_class(&mut self, id: Id) {
+        if let Some(checkpoint) = self.undo.last_mut() {
+            if usize::from(id) < checkpoint.n_ids && !checkpoint.classes.contains_key(&id) {
+                if let Some(class) = self.classes.get(&id) {
+                    let saved = EClass {
+                        id: class.id,
+                        nodes: class.nodes.clone(),
+                        data: (checkpoint.clone_data)(&class.data),
+                        parents: class.parents.clone(),
+                        modified: class.modified,
+                    };
+                    checkpoint.classes.insert(id, saved);
+                }
+            }
+        }
+    }
+
+    // inserts into the hashcons, logging the change for the latest
+    // checkpoint
+    fn memo_insert(&mut self, enode: L, id: Id) -> Option<Id> {
+        match self.undo.last_mut() {
+            None => self.memo.insert(enode, id),
+            Some(checkpoint) => {
+                let old = self.memo.insert(enode.clone(), id);
+                checkpoint.memo.push((enode, old));
+                old
+            }
+        }
+    }
+
+    /// Explains why two terms are equivalent.
+    ///
+    /// Both terms are added to the egraph (if they aren't there already),
+    /// and the returned [`Explanation`] is a chain of rewrites from
+    /// `left` to `right`, each justified by the rule (and substitution)
+    /// or trusted union that caused it.
+    ///
+    /// Panics if explanations are not enabled or if the two terms are
+    /// not equivalent.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let rules: &[Rewrite<S, ()>] = &[
+    ///     rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+    /// ];
+    ///
+    /// let start = "(+ 0 x)".parse().unwrap();
+    /// let mut runner = Runner::default()
+    ///     .with_explanations_enabled()
+    ///     .with_expr(&start)
+    ///     .run(rules);
+    ///
+    /// let explanation = runner.egraph.explain_equivalence(&start, &"x".parse().unwrap());
+    /// println!("{}", explanation);
+    /// assert_eq!(explanation.steps.len(), 2);
+    /// assert_eq!(explanation.end().to_string(), "x");
+    /// ```
+    pub fn explain_equivalence(&mut self, left: &RecExpr<L>, right: &RecExpr<L>) -> Explanation<L> {
+        if self.explain.is_none() {
+            panic!("Use EGraph::with_explanations_enabled before explaining equivalences");
+        }
+
+        let left = self.add_expr_uncanonical(left);
+        let right = self.add_expr_uncanonical(right);
+        if self.find(left) != self.find(right) {
+            // the terms may be equal by congruence, which only rebuilding finds
+            self.rebuild();
+        }
+        assert_eq!(
+            self.find(left),
+            self.find(right),
+            "Cannot explain terms that are not equivalent"
+        );
+
+        self.explain
+            .as_ref()
+            .unwrap()
+            .explain_equivalence(left, right)
+    }
+
     /// Returns a more debug-able representation of the egraph.
     ///
     /// [`EGraph`]s implement [`Debug`], but it ain't pretty. It
@@ -406,6 +1397,36 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
 // All the rebuilding stuff
 impl<L: Language, N: Analysis<L>> EGraph<L, N> {
+    // adds the enodes of every eclass to the hashcons if they are
+    // missing, queueing a union if they are in another eclass, and
+    // recomputes the parents
+    #[cfg(feature = "serde-1")]
+    fn repair_memo(&mut self) {
+        let uf = &self.unionfind;
+        let mut parents: HashMap<Id, Vec<(L, Id)>> = HashMap::default();
+        for class in self.classes.values() {
+            for node in &class.nodes {
+                let node = node.clone().map_children(|child| uf.find(child));
+                node.for_each(|child| {
+                    parents
+                        .entry(child)
+                        .or_default()
+                        .push((node.clone(), class.id))
+                });
+                match self.memo.get(&node).map(|&id| uf.find(id)) {
+                    Some(id) if id == class.id => {}
+                    Some(_) => self.pending.push((node, class.id)),
+                    None => {
+                        self.memo.insert(node, class.id);
+                    }
+                }
+            }
+        }
+        for class in self.classes.values_mut() {
+            class.parents = parents.remove(&class.id).unwrap_or_default();
+        }
+    }
+
     #[inline(never)]
     fn rebuild_classes(&mut self) -> usize {
         let mut classes_by_op = std::mem::take(&mut self.classes_by_op);
@@ -413,13 +1434,34 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
 
         let mut trimmed = 0;
 
+        // save the eclasses whose enodes are about to be canonicalized
+        let compress = self.undo.is_empty();
+        if !compress {
+            let uf = &self.unionfind;
+            let changed: Vec<Id> = self
+                .classes
+                .values()
+                .filter(|class| {
+                    let is_canonical = |n: &L| n.all(|id| uf.find(id) == id);
+                    !class.nodes.iter().all(is_canonical)
+                })
+                .map(|class| class.id)
+                .collect();
+            changed.into_iter().for_each(|id| self.save_class(id));
+        }
+
         let uf = &mut self.unionfind;
-        for class in self.classes.values_mut() {
+        let mut canonicalize = |class: &mut EClass<L, N::Data>| {
             let old_len = class.len();
-            class
-                .nodes
-                .iter_mut()
-                .for_each(|n| n.update_children(|id| uf.find_mut(id)));
+            class.nodes.iter_mut().for_each(|n| {
+                n.update_children(|id| {
+                    if compress {
+                        uf.find_mut(id)
+                    } else {
+                        uf.find(id)
+                    }
+                })
+            });
             class.nodes.sort_unstable();
             class.nodes.dedup();
 
@@ -445,6 +1487,16 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
                     }
                 }
             }
+        };
+
+        if self.deterministic {
+            let mut ids: Vec<Id> = self.classes.keys().copied().collect();
+            ids.sort_unstable();
+            for id in ids {
+                canonicalize(self.classes.get_mut(&id).unwrap());
+            }
+        } else {
+            self.classes.values_mut().for_each(canonicalize);
         }
 
         #[cfg(debug_assertions)]
@@ -477,17 +1529,71 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
             }
         }
 
-        for (n, e) in test_memo {
+        for (n, &e) in &test_memo {
             assert_eq!(e, self.find(e));
             assert_eq!(
                 Some(e),
-                self.memo.get(n).map(|id| self.find(*id)),
+                self.memo.get(*n).map(|id| self.find(*id)),
                 "Entry for {:?} at {} in test_memo was incorrect",
                 n,
                 e
             );
         }
 
+        // every parent entry must point to an enode that is still in
+        // its eclass, and every enode must be a parent of its children
+        let mut test_parents = HashSet::default();
+        for (&id, class) in self.classes.iter() {
+            for (parent, parent_class) in &class.parents {
+                let parent = parent.clone().map_children(|c| self.find(c));
+                let parent_class = self.find(*parent_class);
+                assert_eq!(
+                    test_memo.get(&parent),
+                    Some(&parent_class),
+                    "Parent {:?} of eclass {} is not in eclass {}",
+                    parent,
+                    id,
+                    parent_class
+                );
+                test_parents.insert((id, parent, parent_class));
+            }
+        }
+
+        for (&id, class) in self.classes.iter() {
+            for node in &class.nodes {
+                node.for_each(|child| {
+                    assert!(
+                        test_parents.contains(&(self.find(child), node.clone(), id)),
+                        "Enode {:?} in eclass {} is missing from the parents of eclass {}",
+                        node,
+                        id,
+                        child
+                    );
+                });
+
+                #[allow(clippy::mem_discriminant_non_enum)]
+                let discrim = std::mem::discriminant(node);
+                assert!(
+                    self.classes_by_op
+                        .get(&discrim)
+                        .map_or(false, |ids| ids.contains(&id)),
+                    "Eclass {} is missing from the operator index for {:?}",
+                    id,
+                    node
+                );
+            }
+        }
+
+        for (discrim, ids) in &self.classes_by_op {
+            for &id in ids {
+                #[allow(clippy::mem_discriminant_non_enum)]
+                let has_op = self.classes.get(&id).map_or(false, |class| {
+                    class.iter().any(|n| std::mem::discriminant(n) == *discrim)
+                });
+                assert!(has_op, "Operator index has a stale entry for eclass {}", id);
+            }
+        }
+
         true
     }
 
@@ -498,8 +1604,9 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
         while !self.pending.is_empty() {
             while let Some((mut node, class)) = self.pending.pop() {
                 node.update_children(|id| self.find_mut(id));
-                if let Some(memo_class) = self.memo.insert(node, class) {
-                    let (_, did_something) = self.union(memo_class, class);
+                if let Some(memo_class) = self.memo_insert(node, class) {
+                    let (_, did_something) =
+                        self.perform_union(memo_class, class, Some(Justification::Congruence));
                     n_unions += did_something as usize;
                 }
             }
@@ -507,10 +1614,13 @@ impl<L: Language, N: Analysis<L>> EGraph<L, N> {
             while let Some((node, class_id)) = self.analysis_pending.pop() {
                 let class_id = self.find_mut(class_id);
                 let node_data = N::make(self, &node);
+                self.save_class(class_id);
                 let class = self.classes.get_mut(&class_id).unwrap();
                 match self.analysis.merge(&mut class.data, node_data) {
                     Some(Ordering::Equal) | Some(Ordering::Greater) => {}
                     Some(Ordering::Less) | None => {
+                        self.clock += 1;
+                        class.modified = self.clock;
                         self.analysis_pending.extend(class.parents.iter().cloned());
                         N::modify(self, class_id)
                     }
@@ -625,4 +1735,290 @@ mod tests {
 
         egraph.dot().to_dot("target/foo.dot").unwrap();
     }
+
+    #[test]
+    fn remove_nodes() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let a = egraph.add(S::leaf("a"));
+        let b = egraph.add(S::leaf("b"));
+        let fa = egraph.add(S::new("f", vec![a]));
+        let fb = egraph.add(S::new("f", vec![b]));
+        let ga = egraph.add(S::new("g", vec![a]));
+        let h = egraph.add(S::new("h", vec![fa]));
+        egraph.union(fa, ga);
+        egraph.rebuild();
+
+        // nothing to remove
+        assert!(!egraph.remove_node(fa, &S::new("f", vec![b])));
+        assert_eq!(egraph.prune_class(fa, |_| true), 0);
+
+        assert!(egraph.remove_node(fa, &S::new("f", vec![a])));
+        assert!(egraph.check_memo());
+        assert_eq!(egraph[fa].nodes, vec![S::new("g", vec![a])]);
+        assert_eq!(egraph.lookup(S::new("f", vec![a])), None);
+        assert_eq!(egraph.lookup(S::new("h", vec![ga])), Some(h));
+
+        // merging a and b must not bring the removed enode back
+        egraph.union(a, b);
+        egraph.rebuild();
+        assert!(egraph.check_memo());
+        assert_ne!(egraph.find(fa), egraph.find(fb));
+        assert_eq!(egraph.lookup(S::new("f", vec![a])), Some(egraph.find(fb)));
+
+        // the operator index no longer points at the pruned eclass
+        let pat: Pattern<S> = "(f ?x)".parse().unwrap();
+        let matches = pat.search(&egraph);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].eclass, egraph.find(fb));
+    }
+
+    #[test]
+    fn remove_and_readd_with_explanations() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default().with_explanations_enabled();
+        let a = egraph.add(S::leaf("a"));
+        let fa = egraph.add(S::new("f", vec![a]));
+        let ga = egraph.add(S::new("g", vec![a]));
+        egraph.union(fa, ga);
+        egraph.rebuild();
+
+        assert!(egraph.remove_node(fa, &S::new("f", vec![a])));
+        assert_eq!(egraph.lookup(S::new("f", vec![a])), None);
+
+        // the removed enode comes back in a new eclass
+        let fa2 = egraph.add(S::new("f", vec![a]));
+        egraph.rebuild();
+        assert!(egraph.check_memo());
+        assert_ne!(egraph.find(fa2), egraph.find(ga));
+        assert_eq!(egraph.lookup(S::new("f", vec![a])), Some(egraph.find(fa2)));
+        assert_eq!(egraph[fa2].nodes, vec![S::new("f", vec![a])]);
+    }
+
+    #[test]
+    fn retain_reachable() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let junk = egraph.add_expr(&"(g (h c) d)".parse().unwrap());
+        let a = egraph.add(S::leaf("a"));
+        let b = egraph.add(S::leaf("b"));
+        let fab = egraph.add(S::new("f", vec![a, b]));
+        let x = egraph.add(S::leaf("x"));
+        egraph.union(x, a);
+        let y = egraph.add(S::leaf("y"));
+        egraph.union(y, junk);
+        egraph.rebuild();
+
+        let remap = egraph.retain_reachable(&[fab]);
+        assert!(egraph.check_memo());
+        assert_eq!(egraph.number_of_classes(), 3);
+        assert_eq!(egraph.total_size(), 4);
+        assert_eq!(remap.len(), 4);
+        assert_eq!(remap[&x], remap[&a]);
+        assert!(!remap.contains_key(&junk));
+        assert!(!remap.contains_key(&y));
+
+        let mut new_ids: Vec<Id> = remap.values().copied().collect();
+        new_ids.sort();
+        new_ids.dedup();
+        assert_eq!(new_ids, vec![0.into(), 1.into(), 2.into()]);
+
+        // the compacted egraph is still usable
+        let (a, b) = (remap[&a], remap[&b]);
+        assert_eq!(egraph.lookup(S::new("f", vec![a, b])), Some(remap[&fab]));
+        let fba = egraph.add(S::new("f", vec![b, a]));
+        egraph.union(a, b);
+        egraph.rebuild();
+        assert_eq!(egraph.find(fba), egraph.find(remap[&fab]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot remove every enode")]
+    fn remove_last_node() {
+        use SymbolLang as S;
+
+        let mut egraph = EGraph::<S, ()>::default();
+        let a = egraph.add(S::leaf("a"));
+        egraph.prune_class(a, |_| false);
+    }
+
+    #[test]
+    fn deterministic_order() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let rules: Vec<Rewrite<S, ()>> = vec![
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+            rewrite!("comm-mul"; "(* ?a ?b)" => "(* ?b ?a)"),
+            rewrite!("distribute"; "(* ?a (+ ?b ?c))" => "(+ (* ?a ?b) (* ?a ?c))"),
+            rewrite!("factor"; "(+ (* ?a ?b) (* ?a ?c))" => "(* ?a (+ ?b ?c))"),
+        ];
+        let start: RecExpr<S> = "(* a (+ b (+ c d)))".parse().unwrap();
+        let goal: RecExpr<S> = "(+ (* a d) (+ (* c a) (* b a)))".parse().unwrap();
+
+        let run = |n_junk: usize| {
+            let mut egraph = EGraph::default()
+                .with_explanations_enabled()
+                .with_deterministic_order();
+            // eclasses added first change the layout of the hash tables,
+            // but not the order of the ids added after them
+            for i in 0..n_junk {
+                egraph.add(S::leaf(format!("junk{}", i)));
+            }
+            let runner = Runner::default()
+                .with_egraph(egraph)
+                .with_expr(&start)
+                .with_iter_limit(4)
+                .run(&rules);
+            let root = runner.roots[0];
+            let mut egraph = runner.egraph;
+
+            let (_, best) = Extractor::new(&egraph, AstSize).find_best(root);
+            let explanation = egraph.explain_equivalence(&start, &goal);
+            (best.to_string(), explanation.to_string())
+        };
+
+        let expected = run(0);
+        for &n_junk in &[1, 7, 100] {
+            assert_eq!(run(n_junk), expected);
+        }
+    }
+
+    #[test]
+    fn push_pop() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let fab = egraph.add_expr(&"(f a b)".parse().unwrap());
+        let fcb = egraph.add_expr(&"(f c b)".parse().unwrap());
+        let a = egraph.add(S::leaf("a"));
+        let c = egraph.add(S::leaf("c"));
+        egraph.rebuild();
+        let sizes = |egraph: &EGraph<S, ()>| {
+            (
+                egraph.number_of_classes(),
+                egraph.total_size(),
+                egraph.total_number_of_nodes(),
+            )
+        };
+        let before = sizes(&egraph);
+
+        egraph.push();
+        egraph.union(a, c);
+        egraph.rebuild();
+        assert_eq!(egraph.find(fab), egraph.find(fcb));
+
+        // nested checkpoint, with a pending union when popping
+        egraph.push();
+        let d = egraph.add_expr(&"(g (f a b) d)".parse().unwrap());
+        egraph.union(d, fab);
+        assert_eq!(egraph.checkpoints(), 2);
+        egraph.pop();
+        assert!(egraph.check_memo());
+        assert_eq!(egraph.find(fab), egraph.find(fcb));
+        assert_ne!(egraph.find(fab), egraph.find(a));
+
+        egraph.pop();
+        assert_eq!(egraph.checkpoints(), 0);
+        assert!(egraph.check_memo());
+        assert_eq!(sizes(&egraph), before);
+        assert_ne!(egraph.find(fab), egraph.find(fcb));
+
+        // the egraph is still usable
+        let pat: Pattern<S> = "(f ?x b)".parse().unwrap();
+        assert_eq!(pat.search(&egraph).len(), 2);
+        egraph.union(a, c);
+        egraph.rebuild();
+        assert_eq!(egraph.find(fab), egraph.find(fcb));
+    }
+
+    #[cfg(feature = "serde-1")]
+    #[test]
+    fn serde_roundtrip() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let x = egraph.add(S::leaf("x"));
+        let y = egraph.add(S::leaf("y"));
+        let plus = egraph.add(S::new("+", vec![x, y]));
+        egraph.union(x, y);
+        egraph.rebuild();
+
+        let json = serde_json::to_string(&egraph).unwrap();
+        let mut loaded: EGraph<S, ()> = serde_json::from_str(&json).unwrap();
+        assert!(loaded.check_memo());
+        assert_eq!(loaded.total_size(), egraph.total_size());
+        assert_eq!(loaded.number_of_classes(), egraph.number_of_classes());
+        for id in 0..egraph.unionfind.size() {
+            assert_eq!(loaded.find(Id::from(id)), egraph.find(Id::from(id)));
+        }
+        for class in egraph.classes() {
+            for node in &class.nodes {
+                assert_eq!(loaded.lookup(node.clone()), Some(class.id));
+            }
+        }
+        assert_eq!(loaded.find(x), loaded.find(y));
+        assert_eq!(
+            loaded.lookup(S::new("+", vec![y, x])),
+            Some(loaded.find(plus))
+        );
+
+        // the loaded egraph can still be searched and rewritten
+        let pat: Pattern<S> = "(+ ?a ?a)".parse().unwrap();
+        assert_eq!(pat.search(&loaded).len(), 1);
+        let z = loaded.add(S::leaf("z"));
+        loaded.union(z, plus);
+        loaded.rebuild();
+        assert_eq!(loaded.find(z), loaded.find(plus));
+    }
+
+    #[cfg(feature = "serde-1")]
+    #[test]
+    fn serde_invalid() {
+        use SymbolLang as S;
+
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let x = egraph.add(S::leaf("x"));
+        let y = egraph.add(S::leaf("y"));
+        egraph.add(S::new("+", vec![x, y]));
+        egraph.rebuild();
+        let json = serde_json::to_value(&egraph).unwrap();
+
+        let load = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            edit(&mut json);
+            serde_json::from_value::<EGraph<S, ()>>(json)
+        };
+        assert!(load(&|_| ()).is_ok());
+        // an out of bounds parent
+        let err = load(&|json| json["unionfind"]["parents"][0] = 7.into());
+        assert!(err.is_err());
+        // a cycle in the union-find
+        let err = load(&|json| {
+            json["unionfind"]["parents"][0] = 1.into();
+            json["unionfind"]["parents"][1] = 0.into();
+        });
+        assert!(err.is_err());
+        // a root without an eclass
+        let err = load(&|json| json["classes"].as_array_mut().unwrap().truncate(2));
+        assert!(err.is_err());
+
+        // a missing hashcons is rebuilt from the eclasses
+        let loaded = load(&|json| json["memo"] = serde_json::json!([])).unwrap();
+        assert!(loaded.check_memo());
+        assert_eq!(
+            loaded.lookup(S::new("+", vec![x, y])),
+            egraph.lookup(S::new("+", vec![x, y]))
+        );
+    }
 }
@@ -0,0 +1,367 @@
+use std::fmt::{self, Display};
+
+use crate::*;
+
+/// The reason two e-nodes were unioned.
+///
+/// When explanations are enabled (see
+/// [`EGraph::with_explanations_enabled`]), every union performed by the
+/// [`EGraph`] is recorded together with its [`Justification`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Justification {
+    /// The union was performed by applying the named rewrite with the
+    /// given substitution.
+    Rule {
+        /// The name of the [`Rewrite`] that was applied.
+        name: Symbol,
+        /// The substitution the rewrite was applied with.
+        subst: Subst,
+    },
+    /// The union was asserted by the user, for example through
+    /// [`EGraph::union_trusted`] or a plain [`EGraph::union`].
+    Trusted(Symbol),
+    /// The two e-nodes have the same operator and equivalent children.
+    Congruence,
+}
+
+impl Display for Justification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Justification::Rule { name, .. } => write!(f, "{}", name),
+            Justification::Trusted(reason) => write!(f, "{}", reason),
+            Justification::Congruence => write!(f, "congruence"),
+        }
+    }
+}
+
+/// A step-by-step proof that two terms are equivalent.
+///
+/// Returned by [`EGraph::explain_equivalence`].
+/// Each [`ExplanationStep`] rewrites the term of the previous step
+/// (or `start`, for the first step) into a new term.
+/// Congruence steps are flattened away, so every step is justified
+/// either by a rule or by a trusted union.
+///
+/// The [`Display`] implementation prints one term per line, annotated
+/// with the justification for reaching it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation<L> {
+    /// The term the proof starts from.
+    pub start: RecExpr<L>,
+    /// The rewrite steps, in order.
+    pub steps: Vec<ExplanationStep<L>>,
+}
+
+/// One step of an [`Explanation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplanationStep<L> {
+    /// Why this step is valid.
+    pub justification: Justification,
+    /// `true` if the rule was used right-to-left in this step.
+    pub backward: bool,
+    /// The whole term after this step.
+    pub term: RecExpr<L>,
+}
+
+impl<L> Explanation<L> {
+    /// Returns the term the proof ends at.
+    pub fn end(&self) -> &RecExpr<L> {
+        self.steps.last().map_or(&self.start, |step| &step.term)
+    }
+}
+
+impl<L: Language> Display for Explanation<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.start)?;
+        for step in &self.steps {
+            let arrow = if step.backward { "<=" } else { "=>" };
+            writeln!(f, "{} {}  [{}]", arrow, step.term, step.justification)?;
+        }
+        Ok(())
+    }
+}
+
+/// The proof forest behind explanations.
+///
+/// Every [`Id`] handed out by an explaining [`EGraph`] corresponds to
+/// exactly one e-node with uncanonicalized children, so each id stands
+/// for one concrete term. Unions add an edge between the two ids that
+/// were unioned, so the forest connects exactly the ids the union-find
+/// considers equivalent.
+#[derive(Debug, Clone)]
+pub(crate) struct Explain<L> {
+    explainfind: Vec<ExplainNode<L>>,
+    pub(crate) uncanon_memo: HashMap<L, Id>,
+    // the rewrite whose matches are being applied, see `Rewrite::apply`
+    pub(crate) rule: Option<RuleContext<L>>,
+}
+
+// what an `Applier` needs to justify its unions
+#[derive(Debug, Clone)]
+pub(crate) struct RuleContext<L> {
+    pub(crate) name: Symbol,
+    pub(crate) searcher_ast: Option<PatternAst<L>>,
+}
+
+#[derive(Debug, Clone)]
+struct ExplainNode<L> {
+    node: L,
+    // the next node on the path to the root of this proof tree
+    next: Id,
+    // why this node is equal to `next`, unused at the root
+    justification: Justification,
+    // whether the justification goes from this node to `next`
+    forward: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Term<L> {
+    node: L,
+    children: Vec<Term<L>>,
+}
+
+struct RawStep<L> {
+    justification: Justification,
+    backward: bool,
+    term: Term<L>,
+}
+
+fn children<L: Language>(node: &L) -> Vec<Id> {
+    let mut children = Vec::with_capacity(node.len());
+    node.for_each(|id| children.push(id));
+    children
+}
+
+impl<L: Language> Term<L> {
+    fn add_to(&self, expr: &mut RecExpr<L>) -> Id {
+        let ids: Vec<Id> = self.children.iter().map(|c| c.add_to(expr)).collect();
+        let mut ids = ids.into_iter();
+        let node = self.node.clone().map_children(|_| ids.next().unwrap());
+        expr.add(node)
+    }
+
+    fn to_recexpr(&self) -> RecExpr<L> {
+        let mut expr = RecExpr::default();
+        self.add_to(&mut expr);
+        expr
+    }
+}
+
+impl<L: Language> Explain<L> {
+    pub(crate) fn new() -> Self {
+        Self {
+            explainfind: vec![],
+            uncanon_memo: Default::default(),
+            rule: None,
+        }
+    }
+
+    fn node(&self, id: Id) -> &ExplainNode<L> {
+        &self.explainfind[usize::from(id)]
+    }
+
+    /// Registers a fresh id for the given uncanonical e-node.
+    pub(crate) fn add(&mut self, node: L, id: Id) {
+        assert_eq!(usize::from(id), self.explainfind.len());
+        self.explainfind.push(ExplainNode {
+            node: node.clone(),
+            next: id,
+            justification: Justification::Congruence,
+            forward: true,
+        });
+        self.uncanon_memo.insert(node, id);
+    }
+
+    /// Records that `from` was unioned with `to`.
+    /// The two ids must not be connected yet.
+    pub(crate) fn union(&mut self, from: Id, to: Id, justification: Justification) {
+        self.make_leader(from);
+        let node = &mut self.explainfind[usize::from(from)];
+        node.next = to;
+        node.justification = justification;
+        node.forward = true;
+    }
+
+    // reroots the proof tree containing `id` so that `id` is the root
+    fn make_leader(&mut self, id: Id) {
+        let mut path = vec![id];
+        loop {
+            let last = *path.last().unwrap();
+            let next = self.node(last).next;
+            if next == last {
+                break;
+            }
+            path.push(next);
+        }
+
+        // reverse every edge on the path, moving each justification
+        // from the child to the parent
+        for i in (0..path.len() - 1).rev() {
+            let (child, parent) = (path[i], path[i + 1]);
+            let justification = self.node(child).justification.clone();
+            let forward = self.node(child).forward;
+            let parent = &mut self.explainfind[usize::from(parent)];
+            parent.next = child;
+            parent.justification = justification;
+            parent.forward = !forward;
+        }
+        self.explainfind[usize::from(id)].next = id;
+    }
+
+    fn term(&self, id: Id) -> Term<L> {
+        let node = &self.node(id).node;
+        Term {
+            node: node.clone(),
+            children: children(node).into_iter().map(|c| self.term(c)).collect(),
+        }
+    }
+
+    // the edges on the path from `from` to `to` in the proof forest,
+    // as (start, end, justification, backward)
+    fn path(&self, from: Id, to: Id) -> Vec<(Id, Id, Justification, bool)> {
+        let mut from_chain = vec![from];
+        loop {
+            let last = *from_chain.last().unwrap();
+            let next = self.node(last).next;
+            if next == last {
+                break;
+            }
+            from_chain.push(next);
+        }
+        let position: HashMap<Id, usize> = from_chain
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i))
+            .collect();
+
+        let mut to_chain = vec![to];
+        let ancestor = loop {
+            let last = *to_chain.last().unwrap();
+            if let Some(&i) = position.get(&last) {
+                break i;
+            }
+            let next = self.node(last).next;
+            assert_ne!(next, last, "{} and {} are not equivalent", from, to);
+            to_chain.push(next);
+        };
+
+        let mut edges = vec![];
+        for pair in from_chain[..=ancestor].windows(2) {
+            let node = self.node(pair[0]);
+            edges.push((pair[0], pair[1], node.justification.clone(), !node.forward));
+        }
+        // these edges are stored pointing up, but we walk them down
+        for pair in to_chain.windows(2).rev() {
+            let node = self.node(pair[0]);
+            edges.push((pair[1], pair[0], node.justification.clone(), node.forward));
+        }
+        edges
+    }
+
+    fn explain_ids(&self, from: Id, to: Id) -> Vec<RawStep<L>> {
+        let mut steps = vec![];
+        if from == to {
+            return steps;
+        }
+
+        let mut current = self.term(from);
+        for (start, end, justification, backward) in self.path(from, to) {
+            if let Justification::Congruence = justification {
+                // lift the proofs of the children into the current term
+                let start_children = children(&self.node(start).node);
+                let end_children = children(&self.node(end).node);
+                debug_assert_eq!(start_children.len(), end_children.len());
+                for (i, (&a, &b)) in start_children.iter().zip(&end_children).enumerate() {
+                    for step in self.explain_ids(a, b) {
+                        current.children[i] = step.term;
+                        steps.push(RawStep {
+                            justification: step.justification,
+                            backward: step.backward,
+                            term: current.clone(),
+                        });
+                    }
+                }
+                current.node = self.node(end).node.clone();
+            } else {
+                current = self.term(end);
+                steps.push(RawStep {
+                    justification,
+                    backward,
+                    term: current.clone(),
+                });
+            }
+        }
+        steps
+    }
+
+    pub(crate) fn explain_equivalence(&self, left: Id, right: Id) -> Explanation<L> {
+        let steps = self
+            .explain_ids(left, right)
+            .into_iter()
+            .map(|step| ExplanationStep {
+                justification: step.justification,
+                backward: step.backward,
+                term: step.term.to_recexpr(),
+            })
+            .collect();
+        Explanation {
+            start: self.term(left).to_recexpr(),
+            steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn explain_rules() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+        ];
+
+        let start: RecExpr<S> = "(+ a (+ b c))".parse().unwrap();
+        let goal: RecExpr<S> = "(+ c (+ b a))".parse().unwrap();
+        let mut runner = Runner::default()
+            .with_explanations_enabled()
+            .with_expr(&start)
+            .run(rules);
+
+        let explanation = runner.egraph.explain_equivalence(&start, &goal);
+        assert_eq!(explanation.start, start);
+        assert_eq!(explanation.end(), &goal);
+        for step in &explanation.steps {
+            match &step.justification {
+                Justification::Rule { name, .. } => {
+                    assert!(rules.iter().any(|r| r.name() == name.as_str()))
+                }
+                j => panic!("Unexpected justification {:?}", j),
+            }
+        }
+    }
+
+    #[test]
+    fn explain_congruence() {
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default().with_explanations_enabled();
+        let fa: RecExpr<S> = "(f a)".parse().unwrap();
+        let fb: RecExpr<S> = "(f b)".parse().unwrap();
+        egraph.add_expr(&fa);
+        egraph.add_expr(&fb);
+        let a = egraph.add(S::leaf("a"));
+        let b = egraph.add(S::leaf("b"));
+        egraph.union_trusted(a, b, "axiom");
+        egraph.rebuild();
+
+        let explanation = egraph.explain_equivalence(&fa, &fb);
+        assert_eq!(explanation.steps.len(), 1);
+        assert_eq!(explanation.end(), &fb);
+        assert_eq!(
+            explanation.steps[0].justification,
+            Justification::Trusted("axiom".into())
+        );
+    }
+}
@@ -117,6 +117,7 @@ assert_eq!(AstSize.cost_rec(&e), 4);
 ```
 
 **/
+#[derive(Debug, Clone, Copy)]
 pub struct AstSize;
 impl<L: Language> CostFunction<L> for AstSize {
     type Cost = usize;
@@ -137,6 +138,7 @@ assert_eq!(AstDepth.cost_rec(&e), 2);
 ```
 
 **/
+#[derive(Debug, Clone, Copy)]
 pub struct AstDepth;
 impl<L: Language> CostFunction<L> for AstDepth {
     type Cost = usize;
@@ -235,22 +237,31 @@ where
     }
 
     fn find_costs(&mut self) {
+        let egraph = self.egraph;
+        // only sort the eclasses if ties must be broken the same way
+        // every time, see `EGraph::with_deterministic_order`
+        let sorted = if egraph.is_deterministic() {
+            let mut classes: Vec<&EClass<L, N::Data>> = egraph.classes().collect();
+            classes.sort_unstable_by_key(|class| class.id);
+            Some(classes)
+        } else {
+            None
+        };
+
         let mut did_something = true;
         while did_something {
             did_something = false;
 
-            for class in self.egraph.classes() {
-                let pass = self.make_pass(class);
-                match (self.costs.get(&class.id), pass) {
-                    (None, Some(new)) => {
-                        self.costs.insert(class.id, new);
-                        did_something = true;
+            match &sorted {
+                Some(classes) => {
+                    for class in classes {
+                        did_something |= self.update_cost(class);
                     }
-                    (Some(old), Some(new)) if new.0 < old.0 => {
-                        self.costs.insert(class.id, new);
-                        did_something = true;
+                }
+                None => {
+                    for class in egraph.classes() {
+                        did_something |= self.update_cost(class);
                     }
-                    _ => (),
                 }
             }
         }
@@ -266,6 +277,22 @@ where
         }
     }
 
+    // returns whether the eclass got a cost or a lower one
+    fn update_cost(&mut self, class: &EClass<L, N::Data>) -> bool {
+        let pass = self.make_pass(class);
+        match (self.costs.get(&class.id), pass) {
+            (None, Some(new)) => {
+                self.costs.insert(class.id, new);
+                true
+            }
+            (Some(old), Some(new)) if new.0 < old.0 => {
+                self.costs.insert(class.id, new);
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn make_pass(&mut self, eclass: &EClass<L, N::Data>) -> Option<(CF::Cost, L)> {
         let (cost, node) = eclass
             .iter()
@@ -62,7 +62,7 @@ pub trait Language: Debug + Clone + Eq + Ord + Hash {
     /// The [`define_language!`] macro will
     /// implement this for you.
     #[allow(unused_variables)]
-    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, String> {
+    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, FromOpError> {
         unimplemented!("from_op_str not implemented")
     }
 
@@ -162,6 +162,12 @@ pub trait LanguageChildren {
     fn len(&self) -> usize;
     /// Checks if n is an acceptable number of children for this type.
     fn can_be_length(n: usize) -> bool;
+    /// Returns the only acceptable number of children for this type, if
+    /// there is just one.
+    /// This is only used to report parse errors; by default it returns `None`.
+    fn fixed_length() -> Option<usize> {
+        None
+    }
     /// Create an instance of this type from a `Vec<Id>`,
     /// with the guarantee that can_be_length is already true on the `Vec`.
     fn from_vec(v: Vec<Id>) -> Self;
@@ -177,6 +183,7 @@ macro_rules! impl_array {
         impl LanguageChildren for [Id; $n] {
             fn len(&self) -> usize                   { <[Id]>::len(self) }
             fn can_be_length(n: usize) -> bool       { n == $n }
+            fn fixed_length() -> Option<usize>       { Some($n) }
             fn from_vec(v: Vec<Id>) -> Self          { Self::try_from(v.as_slice()).unwrap() }
             fn as_slice(&self) -> &[Id]              { self }
             fn as_mut_slice(&mut self) -> &mut [Id]  { self }
@@ -209,6 +216,7 @@ impl LanguageChildren for Vec<Id> {
 impl LanguageChildren for Id {
     fn len(&self) -> usize                   { 1 }
     fn can_be_length(n: usize) -> bool       { n == 1 }
+    fn fixed_length() -> Option<usize>       { Some(1) }
     fn from_vec(v: Vec<Id>) -> Self          { v[0] }
     fn as_slice(&self) -> &[Id]              { std::slice::from_ref(self) }
     fn as_mut_slice(&mut self) -> &mut [Id]  { std::slice::from_mut(self) }
@@ -223,9 +231,13 @@ impl LanguageChildren for Id {
 /// elements that come before it in the list.
 ///
 /// If the `serde-1` feature is enabled, this implements
-/// [`serde::Serialize`][https://docs.rs/serde/latest/serde/trait.Serialize.html].
+/// [`serde::Serialize`](https://docs.rs/serde/latest/serde/trait.Serialize.html)
+/// and
+/// [`serde::Deserialize`](https://docs.rs/serde/latest/serde/trait.Deserialize.html)
+/// using the same s-expression string as [`Display`] and [`FromStr`].
 ///
 /// [pretty]: RecExpr::pretty()
+/// [`FromStr`]: std::str::FromStr
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RecExpr<L> {
     nodes: Vec<L>,
@@ -242,6 +254,17 @@ impl<L: Language> serde::Serialize for RecExpr<L> {
     }
 }
 
+#[cfg(feature = "serde-1")]
+impl<'de, L: Language> serde::Deserialize<'de> for RecExpr<L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str> as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl<L> Default for RecExpr<L> {
     fn default() -> Self {
         Self::from(vec![])
@@ -362,45 +385,275 @@ impl<L: Language> RecExpr<L> {
     }
 }
 
-macro_rules! bail {
-    ($s:literal $(,)?) => {
-        return Err($s.into())
-    };
-    ($s:literal, $($args:expr),+) => {
-        return Err(format!($s, $($args),+).into())
-    };
+/// An error from [`Language::from_op_str`]: the operator and children
+/// don't make up a valid enode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromOpError {
+    /// The operator that couldn't be parsed.
+    pub op: String,
+    /// The number of children the operator was given.
+    pub arity: usize,
+    /// The numbers of children the operator takes, in increasing order,
+    /// if it is a known operator.
+    /// An operator can take several if different variants of the
+    /// [`Language`] share it.
+    pub expected_arities: Vec<usize>,
+    /// What went wrong.
+    pub message: String,
 }
 
-impl<L: Language> std::str::FromStr for RecExpr<L> {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        fn parse_sexp_into<L: Language>(sexp: &Sexp, expr: &mut RecExpr<L>) -> Result<Id, String> {
-            match sexp {
-                Sexp::Empty => Err("Found empty s-expression".into()),
-                Sexp::String(s) => {
-                    let node = L::from_op_str(s, vec![])?;
-                    Ok(expr.add(node))
+impl FromOpError {
+    /// Creates a new [`FromOpError`] for `op` given `arity` children.
+    pub fn new(op: impl Into<String>, arity: usize, message: impl Into<String>) -> Self {
+        Self {
+            op: op.into(),
+            arity,
+            expected_arities: vec![],
+            message: message.into(),
+        }
+    }
+
+    /// Records a number of children the operator takes.
+    pub fn with_expected_arity(mut self, expected_arity: usize) -> Self {
+        if let Err(i) = self.expected_arities.binary_search(&expected_arity) {
+            self.expected_arities.insert(i, expected_arity);
+        }
+        self
+    }
+}
+
+impl Display for FromOpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} for '{}' with {} children",
+            self.message, self.op, self.arity
+        )?;
+        if let Some((last, rest)) = self.expected_arities.split_last() {
+            write!(f, " (expected ")?;
+            for (i, arity) in rest.iter().enumerate() {
+                let sep = if i + 1 == rest.len() { " or" } else { "," };
+                write!(f, "{}{} ", arity, sep)?;
+            }
+            write!(f, "{})", last)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FromOpError {}
+
+/// An error from parsing a [`RecExpr`] (or a [`Pattern`]) from a string.
+///
+/// The error points at the offending part of the input: `line` and
+/// `column` (both starting at 1) locate its start, and `token` is its
+/// text, e.g. the whole sub-expression whose operator failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecExprParseError {
+    /// What went wrong.
+    pub kind: RecExprParseErrorKind,
+    /// The line the offending input starts on.
+    pub line: usize,
+    /// The column the offending input starts at, counted in characters.
+    pub column: usize,
+    /// The offending input.
+    pub token: String,
+}
+
+/// The ways parsing a [`RecExpr`] can fail; see [`RecExprParseError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecExprParseErrorKind {
+    /// The input or a list in it was empty.
+    Empty,
+    /// A list was found where an operator was expected.
+    HeadList,
+    /// A `)` didn't match any `(`.
+    UnexpectedClose,
+    /// A `(` was never closed.
+    Unclosed,
+    /// There was more input after the expression.
+    TrailingInput,
+    /// [`Language::from_op_str`] failed.
+    BadOp(FromOpError),
+}
+
+impl Display for RecExprParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecExprParseErrorKind::Empty => write!(f, "empty expression"),
+            RecExprParseErrorKind::HeadList => write!(f, "expected an operator, found a list"),
+            RecExprParseErrorKind::UnexpectedClose => write!(f, "unexpected ')'"),
+            RecExprParseErrorKind::Unclosed => write!(f, "unclosed '('"),
+            RecExprParseErrorKind::TrailingInput => write!(f, "unexpected input after expression"),
+            RecExprParseErrorKind::BadOp(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Display for RecExprParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}, at '{}'",
+            self.line, self.column, self.kind, self.token
+        )
+    }
+}
+
+impl std::error::Error for RecExprParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Open,
+    Close,
+    Atom,
+}
+
+// a token and its byte range in the input
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            c if c.is_whitespace() => continue,
+            _ => TokenKind::Atom,
+        };
+        let mut end = start + c.len_utf8();
+        if c == '"' {
+            // quoted atoms run until the closing quote
+            for (i, c) in &mut chars {
+                end = i + c.len_utf8();
+                if c == '"' {
+                    break;
+                }
+            }
+        } else if kind == TokenKind::Atom {
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+        }
+        tokens.push(Token { kind, start, end });
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, kind: RecExprParseErrorKind, start: usize, end: usize) -> RecExprParseError {
+        let before = &self.input[..start];
+        RecExprParseError {
+            kind,
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap().chars().count() + 1,
+            token: self.input[start..end].trim_end().to_string(),
+        }
+    }
+
+    // quoted atoms keep their quotes, as they did before
+    fn op(&self, token: Token) -> &'a str {
+        &self.input[token.start..token.end]
+    }
+
+    // the end of the list opened by the given token, or of the input
+    // if the list is unclosed
+    fn list_end(&self, open: usize) -> usize {
+        let mut depth = 0;
+        for token in &self.tokens[open..] {
+            match token.kind {
+                TokenKind::Open => depth += 1,
+                TokenKind::Close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return token.end;
+                    }
                 }
-                Sexp::List(list) if list.is_empty() => Err("Found empty s-expression".into()),
-                Sexp::List(list) => match &list[0] {
-                    Sexp::Empty => unreachable!("Cannot be in head position"),
-                    Sexp::List(l) => bail!("Found a list in the head position: {:?}", l),
-                    Sexp::String(op) => {
-                        let arg_ids: Result<Vec<Id>, _> =
-                            list[1..].iter().map(|s| parse_sexp_into(s, expr)).collect();
-
-                        let node = L::from_op_str(op, arg_ids?).map_err(|e| {
-                            format!("Failed to parse '{}', error message:\n{}", sexp, e)
-                        })?;
-                        Ok(expr.add(node))
+                TokenKind::Atom => (),
+            }
+        }
+        self.input.len()
+    }
+
+    fn parse_into<L: Language>(&mut self, expr: &mut RecExpr<L>) -> Result<Id, RecExprParseError> {
+        use RecExprParseErrorKind::*;
+        let open = self.tokens[self.next];
+        self.next += 1;
+        match open.kind {
+            TokenKind::Close => Err(self.error(UnexpectedClose, open.start, open.end)),
+            TokenKind::Atom => {
+                let node = L::from_op_str(self.op(open), vec![])
+                    .map_err(|err| self.error(BadOp(err), open.start, open.end))?;
+                Ok(expr.add(node))
+            }
+            TokenKind::Open => {
+                let head = match self.tokens.get(self.next) {
+                    Some(&head) => head,
+                    None => return Err(self.error(Unclosed, open.start, self.input.len())),
+                };
+                match head.kind {
+                    TokenKind::Close => return Err(self.error(Empty, open.start, head.end)),
+                    TokenKind::Open => {
+                        let end = self.list_end(self.next);
+                        return Err(self.error(HeadList, head.start, end));
                     }
-                },
+                    TokenKind::Atom => self.next += 1,
+                }
+
+                let mut children = vec![];
+                let close = loop {
+                    match self.tokens.get(self.next) {
+                        None => return Err(self.error(Unclosed, open.start, self.input.len())),
+                        Some(&token) if token.kind == TokenKind::Close => {
+                            self.next += 1;
+                            break token;
+                        }
+                        Some(_) => children.push(self.parse_into(expr)?),
+                    }
+                };
+
+                let node = L::from_op_str(self.op(head), children)
+                    .map_err(|err| self.error(BadOp(err), open.start, close.end))?;
+                Ok(expr.add(node))
             }
         }
+    }
+}
+
+impl<L: Language> std::str::FromStr for RecExpr<L> {
+    type Err = RecExprParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: s,
+            tokens: tokenize(s),
+            next: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err(parser.error(RecExprParseErrorKind::Empty, 0, s.len()));
+        }
 
         let mut expr = RecExpr::default();
-        let sexp = symbolic_expressions::parser::parse_str(s.trim()).map_err(|e| e.to_string())?;
-        parse_sexp_into(&sexp, &mut expr)?;
+        parser.parse_into(&mut expr)?;
+        if let Some(&token) = parser.tokens.get(parser.next) {
+            let kind = RecExprParseErrorKind::TrailingInput;
+            return Err(parser.error(kind, token.start, s.len()));
+        }
         Ok(expr)
     }
 }
@@ -521,6 +774,21 @@ pub trait Analysis<L: Language>: Sized {
     /// By default this does nothing.
     #[allow(unused_variables)]
     fn modify(egraph: &mut EGraph<L, Self>, id: Id) {}
+
+    /// Returns how many bytes of heap memory the given data owns,
+    /// not counting the `Data` value itself.
+    ///
+    /// This is only used to [estimate](EGraph::estimated_memory())
+    /// how much memory an [`EGraph`] uses, for example for
+    /// [`Runner::with_memory_limit`].
+    /// If your `Data` holds large heap allocations (like `Vec`s or
+    /// `String`s), override this so the estimate accounts for them.
+    ///
+    /// By default this returns 0.
+    #[allow(unused_variables)]
+    fn data_heap_size(&self, data: &Self::Data) -> usize {
+        0
+    }
 }
 
 impl<L: Language> Analysis<L> for () {
@@ -555,6 +823,7 @@ pub fn merge_min<T: Ord>(to: &mut T, from: T) -> Ordering {
 
 /// A simple language used for testing.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub struct SymbolLang {
     /// The operator for an enode
     pub op: Symbol,
@@ -584,7 +853,7 @@ impl Language for SymbolLang {
         &self.op
     }
 
-    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, String> {
+    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, FromOpError> {
         Ok(Self {
             op: op_str.into(),
             children,
@@ -599,3 +868,102 @@ impl Language for SymbolLang {
         self.children.iter_mut().for_each(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    define_language! {
+        enum Math {
+            Num(i32),
+            "+" = Add([Id; 2]),
+            "-" = Neg(Id),
+            "-" = Sub([Id; 2]),
+        }
+    }
+
+    fn parse_err(s: &str) -> RecExprParseError {
+        s.parse::<RecExpr<Math>>().unwrap_err()
+    }
+
+    #[test]
+    fn parse_error_positions() {
+        use RecExprParseErrorKind::*;
+
+        let err = parse_err("(+ 1\n   (+ 2 3 4))");
+        assert_eq!((err.line, err.column), (2, 4));
+        assert_eq!(err.token, "(+ 2 3 4)");
+        match err.kind {
+            BadOp(op) => {
+                assert_eq!(op.op, "+");
+                assert_eq!(op.arity, 3);
+                assert_eq!(op.expected_arities, vec![2]);
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        }
+
+        let err = parse_err("(- (* 1 2))");
+        assert_eq!(
+            (err.line, err.column, err.token.as_str()),
+            (1, 4, "(* 1 2)")
+        );
+        assert!(matches!(
+            err.kind,
+            BadOp(FromOpError {
+                ref expected_arities,
+                ..
+            }) if expected_arities.is_empty()
+        ));
+
+        // both variants for "-" are expected
+        let err = parse_err("(- 1 2 3)");
+        match err.kind {
+            BadOp(op) => {
+                assert_eq!(op.expected_arities, vec![1, 2]);
+                assert!(op.to_string().ends_with("(expected 1 or 2)"));
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        }
+
+        let err = parse_err("(+ 1 ())");
+        assert_eq!((err.kind, err.column, err.token.as_str()), (Empty, 6, "()"));
+        let err = parse_err("((+ 1 2) 3)");
+        assert_eq!(
+            (err.kind, err.column, err.token.as_str()),
+            (HeadList, 2, "(+ 1 2)")
+        );
+        let err = parse_err("(+ 1 2");
+        assert_eq!((err.kind, err.column), (Unclosed, 1));
+        let err = parse_err("(- 1) 2");
+        assert_eq!(
+            (err.kind, err.column, err.token.as_str()),
+            (TrailingInput, 7, "2")
+        );
+        let err = parse_err(")");
+        assert_eq!(err.kind, UnexpectedClose);
+        assert_eq!(parse_err("  ").kind, Empty);
+
+        assert_eq!(
+            parse_err("(+ 1\n   (+ 2 3 4))").to_string(),
+            "2:4: wrong number of children for '+' with 3 children (expected 2), at '(+ 2 3 4)'"
+        );
+    }
+
+    #[test]
+    fn parse_pattern_errors() {
+        let err = "(+ ?a (?b 1))".parse::<Pattern<Math>>().unwrap_err();
+        assert_eq!((err.line, err.column, err.token.as_str()), (1, 7, "(?b 1)"));
+
+        let expr: RecExpr<Math> = " (+ 1\n(- 2)) ".parse().unwrap();
+        assert_eq!(expr.to_string(), "(+ 1 (- 2))");
+    }
+
+    #[test]
+    fn parse_quoted_atoms() {
+        // a quoted atom is one token, passed to from_op_str with its quotes
+        let expr: RecExpr<SymbolLang> = "(f \"a b\" \"(c)\")".parse().unwrap();
+        let ops: Vec<&str> = expr.as_ref().iter().map(|n| n.op.as_str()).collect();
+        assert_eq!(ops, vec!["\"a b\"", "\"(c)\"", "f"]);
+        assert!(expr.as_ref()[..2].iter().all(|n| n.is_leaf()));
+    }
+}
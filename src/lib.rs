@@ -23,11 +23,16 @@ pub mod tutorials;
 mod dot;
 mod eclass;
 mod egraph;
+mod explain;
 mod extract;
 mod language;
+#[cfg(feature = "lp")]
+mod lp_extract;
 mod machine;
+mod multipattern;
 mod pattern;
 mod rewrite;
+mod rule_parser;
 mod run;
 mod subst;
 mod unionfind;
@@ -36,6 +41,7 @@ mod util;
 /// A key to identify [`EClass`]es within an
 /// [`EGraph`].
 #[derive(Clone, Copy, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Id(u32);
 
 impl From<usize> for Id {
@@ -62,21 +68,30 @@ impl std::fmt::Display for Id {
     }
 }
 
-pub(crate) use unionfind::UnionFind;
+pub(crate) use {
+    explain::{Explain, RuleContext},
+    unionfind::UnionFind,
+};
 
 pub use {
     dot::Dot,
     eclass::EClass,
     egraph::EGraph,
+    explain::{Explanation, ExplanationStep, Justification},
     extract::*,
     language::*,
+    multipattern::{MultiPattern, MultiPatternParseError},
     pattern::{ENodeOrVar, Pattern, PatternAst, SearchMatches},
     rewrite::{Applier, Condition, ConditionEqual, ConditionalApplier, Rewrite, Searcher},
+    rule_parser::RuleParser,
     run::*,
     subst::{Subst, Var},
     util::*,
 };
 
+#[cfg(feature = "lp")]
+pub use lp_extract::*;
+
 #[cfg(test)]
 fn init_logger() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -0,0 +1,282 @@
+use microlp::{ComparisonOp, LinearExpr, OptimizationDirection, Problem, Variable};
+
+use crate::util::{HashMap, IndexMap};
+use crate::*;
+
+/** A cost function that can be used by an [`LpExtractor`].
+
+Unlike a [`CostFunction`], an [`LpCostFunction`] only assigns a cost
+to each e-node on its own; the cost of a term is the sum of the costs
+of the e-nodes it uses, with each shared e-node counted once.
+
+```
+# use egg::*;
+struct OpCost;
+impl LpCostFunction<SymbolLang, ()> for OpCost {
+    fn node_cost(&mut self, _egraph: &EGraph<SymbolLang, ()>, _eclass: Id, enode: &SymbolLang) -> f64 {
+        match enode.op.as_str() {
+            "*" => 4.0,
+            _ => 1.0,
+        }
+    }
+}
+```
+**/
+pub trait LpCostFunction<L: Language, N: Analysis<L>> {
+    /// Returns the cost of the given e-node.
+    ///
+    /// This function may look at other parts of the e-graph to compute
+    /// the cost of the given e-node.
+    fn node_cost(&mut self, egraph: &EGraph<L, N>, eclass: Id, enode: &L) -> f64;
+}
+
+impl<L: Language, N: Analysis<L>> LpCostFunction<L, N> for AstSize {
+    fn node_cost(&mut self, _egraph: &EGraph<L, N>, _eclass: Id, _enode: &L) -> f64 {
+        1.0
+    }
+}
+
+struct ClassVars {
+    active: Variable,
+    order: Variable,
+    nodes: Vec<Variable>,
+}
+
+/** Extracting [`RecExpr`]s from an [`EGraph`] by solving an integer
+linear program.
+
+The greedy [`Extractor`] finds the cheapest _tree_ in each eclass,
+so it cannot take advantage of common subexpressions.
+An [`LpExtractor`] instead finds a term whose cost as a DAG is
+minimal, counting every shared e-node once.
+Cycles are ruled out with ordering constraints on the eclasses, so the
+result is always a finite term.
+
+Solving the integer program is much slower than greedy extraction,
+so this is best suited for small e-graphs or when sharing matters.
+This requires the `lp` feature.
+
+```
+use egg::*;
+
+let mut egraph = EGraph::<SymbolLang, ()>::default();
+let shared = egraph.add_expr(&"(pair (f (h a b)) (g (h a b)))".parse().unwrap());
+let alternative = egraph.add_expr(&"(pair (m n o) (p q r))".parse().unwrap());
+egraph.union(shared, alternative);
+egraph.rebuild();
+
+// the greedy extractor picks the smaller trees...
+let (_, best) = Extractor::new(&egraph, AstSize).find_best(shared);
+assert_eq!(best.to_string(), "(pair (m n o) (p q r))");
+
+// ...but the other term is smaller once (h a b) is shared
+let best = LpExtractor::new(&egraph, AstSize).solve(shared);
+assert_eq!(best.to_string(), "(pair (f (h a b)) (g (h a b)))");
+assert_eq!(best.as_ref().len(), 6);
+```
+**/
+pub struct LpExtractor<'a, L: Language, N: Analysis<L>> {
+    egraph: &'a EGraph<L, N>,
+    problem: Problem,
+    // in the egraph's order, see `EGraph::with_deterministic_order`
+    vars: IndexMap<Id, ClassVars>,
+}
+
+impl<'a, L, N> LpExtractor<'a, L, N>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    /// Create an [`LpExtractor`] for the given [`EGraph`] and
+    /// [`LpCostFunction`].
+    ///
+    /// This builds the integer program, but doesn't solve it;
+    /// use [`solve`](LpExtractor::solve) for that.
+    /// The e-graph should be [rebuilt](EGraph::rebuild) first.
+    pub fn new<CF>(egraph: &'a EGraph<L, N>, mut cost_function: CF) -> Self
+    where
+        CF: LpCostFunction<L, N>,
+    {
+        // only sort the eclasses if the program must come out the same
+        // every time, see `EGraph::with_deterministic_order`
+        let (problem, vars) = if egraph.is_deterministic() {
+            let mut classes: Vec<&EClass<L, N::Data>> = egraph.classes().collect();
+            classes.sort_unstable_by_key(|class| class.id);
+            Self::build(egraph, &mut cost_function, || classes.iter().copied())
+        } else {
+            Self::build(egraph, &mut cost_function, || egraph.classes())
+        };
+
+        Self {
+            egraph,
+            problem,
+            vars,
+        }
+    }
+
+    // builds the integer program, going through the eclasses in the
+    // order `classes` gives them
+    fn build<CF, I>(
+        egraph: &'a EGraph<L, N>,
+        cost_function: &mut CF,
+        classes: impl Fn() -> I,
+    ) -> (Problem, IndexMap<Id, ClassVars>)
+    where
+        CF: LpCostFunction<L, N>,
+        I: Iterator<Item = &'a EClass<L, N::Data>>,
+    {
+        let max_order = egraph.number_of_classes() as f64;
+        let mut problem = Problem::new(OptimizationDirection::Minimize);
+
+        let mut vars = IndexMap::default();
+        for class in classes() {
+            let nodes = class
+                .iter()
+                .map(|n| problem.add_binary_var(cost_function.node_cost(egraph, class.id, n)))
+                .collect();
+            let class_vars = ClassVars {
+                active: problem.add_binary_var(0.0),
+                order: problem.add_var(0.0, (0.0, max_order)),
+                nodes,
+            };
+            vars.insert(class.id, class_vars);
+        }
+
+        for class in classes() {
+            let class_vars = &vars[&class.id];
+
+            // an active class picks exactly one node, an inactive one none
+            let mut expr: LinearExpr = class_vars.nodes.iter().map(|&n| (n, 1.0)).collect();
+            expr.add(class_vars.active, -1.0);
+            problem.add_constraint(expr, ComparisonOp::Eq, 0.0);
+
+            for (node, &node_var) in class.iter().zip(&class_vars.nodes) {
+                let mut self_loop = false;
+                node.for_each(|child| {
+                    let child = egraph.find(child);
+                    if child == class.id {
+                        self_loop = true;
+                        return;
+                    }
+                    let child_vars = &vars[&child];
+
+                    // picking a node makes its children active
+                    problem.add_constraint(
+                        [(node_var, 1.0), (child_vars.active, -1.0)],
+                        ComparisonOp::Le,
+                        0.0,
+                    );
+
+                    // picking a node orders its children after its class,
+                    // so the picked nodes can't form a cycle
+                    problem.add_constraint(
+                        [
+                            (class_vars.order, 1.0),
+                            (child_vars.order, -1.0),
+                            (node_var, max_order + 1.0),
+                        ],
+                        ComparisonOp::Le,
+                        max_order,
+                    );
+                });
+
+                if self_loop {
+                    problem.add_constraint([(node_var, 1.0)], ComparisonOp::Le, 0.0);
+                }
+            }
+        }
+
+        (problem, vars)
+    }
+
+    /// Find the cheapest term represented in the given eclass.
+    ///
+    /// Panics if the integer program can't be solved, for example if
+    /// every term in the eclass is cyclic.
+    pub fn solve(&mut self, root: Id) -> RecExpr<L> {
+        self.solve_multiple(&[root]).0
+    }
+
+    /// Find the cheapest terms represented in the given eclasses,
+    /// sharing e-nodes between them.
+    ///
+    /// Returns a single [`RecExpr`] holding all the terms and the
+    /// index of each root in it.
+    pub fn solve_multiple(&mut self, roots: &[Id]) -> (RecExpr<L>, Vec<Id>) {
+        let egraph = self.egraph;
+        let mut problem = self.problem.clone();
+        for &root in roots {
+            let root = egraph.find(root);
+            problem.add_constraint([(self.vars[&root].active, 1.0)], ComparisonOp::Eq, 1.0);
+        }
+
+        let solution = problem
+            .solve()
+            .unwrap_or_else(|err| panic!("Failed to solve extraction problem: {}", err));
+        log::info!("Extracted with total cost {}", solution.objective());
+
+        let mut best_nodes: HashMap<Id, &L> = HashMap::default();
+        for (&id, class_vars) in &self.vars {
+            let class = &egraph[id];
+            if let Some((node, _)) = class
+                .iter()
+                .zip(&class_vars.nodes)
+                .find(|(_, var)| solution[**var] > 0.5)
+            {
+                best_nodes.insert(id, node);
+            }
+        }
+
+        let mut expr = RecExpr::default();
+        let mut added_memo: HashMap<Id, Id> = HashMap::default();
+        let root_idxs = roots
+            .iter()
+            .map(|&root| add_best(egraph, &best_nodes, &mut added_memo, &mut expr, root))
+            .collect();
+        (expr, root_idxs)
+    }
+}
+
+fn add_best<L, N>(
+    egraph: &EGraph<L, N>,
+    best_nodes: &HashMap<Id, &L>,
+    added_memo: &mut HashMap<Id, Id>,
+    expr: &mut RecExpr<L>,
+    eclass: Id,
+) -> Id
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    let id = egraph.find(eclass);
+    if let Some(&id_expr) = added_memo.get(&id) {
+        return id_expr;
+    }
+    let node = best_nodes[&id]
+        .clone()
+        .map_children(|child| add_best(egraph, best_nodes, added_memo, expr, child));
+    let id_expr = expr.add(node);
+    added_memo.insert(id, id_expr);
+    id_expr
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    #[test]
+    fn lp_extract_avoids_cycles() {
+        crate::init_logger();
+        let mut egraph = EGraph::<S, ()>::default();
+        let x = egraph.add(S::leaf("x"));
+        let plus = egraph.add_expr(&"(+ x 0)".parse().unwrap());
+        egraph.union(x, plus);
+        let root = egraph.add(S::new("f", vec![x, x]));
+        egraph.rebuild();
+
+        let (best, roots) = LpExtractor::new(&egraph, AstSize).solve_multiple(&[root, plus]);
+        assert_eq!(best.to_string(), "(f x x)");
+        assert_eq!(best.as_ref().len(), 2);
+        assert_eq!(roots, vec![Id::from(1), Id::from(0)]);
+    }
+}
@@ -25,6 +25,7 @@ pub struct Program<L> {
 enum Instruction<L> {
     Bind { node: L, i: Reg, out: Reg },
     Compare { i: Reg, j: Reg },
+    Scan { op: Option<L>, out: Reg },
 }
 
 #[inline(always)]
@@ -101,6 +102,39 @@ impl Machine {
                         return;
                     }
                 }
+                Instruction::Scan { op, out } => {
+                    let remaining_instructions = instructions.as_slice();
+                    let mut run = |id: Id| {
+                        self.reg.truncate(out.0 as usize);
+                        self.reg.push(id);
+                        self.run(egraph, remaining_instructions, subst, yield_fn)
+                    };
+                    match op {
+                        Some(op) => {
+                            #[allow(clippy::mem_discriminant_non_enum)]
+                            let key = std::mem::discriminant(op);
+                            if let Some(ids) = egraph.classes_by_op.get(&key) {
+                                if egraph.is_deterministic() {
+                                    let mut ids: Vec<Id> = ids.iter().copied().collect();
+                                    ids.sort_unstable();
+                                    ids.into_iter().for_each(run);
+                                } else {
+                                    ids.iter().for_each(|&id| run(id));
+                                }
+                            }
+                        }
+                        None => {
+                            if egraph.is_deterministic() {
+                                let mut ids: Vec<Id> = egraph.classes().map(|c| c.id).collect();
+                                ids.sort_unstable();
+                                ids.into_iter().for_each(run);
+                            } else {
+                                egraph.classes().for_each(|class| run(class.id));
+                            }
+                        }
+                    }
+                    return;
+                }
             }
         }
 
@@ -138,36 +172,71 @@ impl<L: Language> Ord for Todo<L> {
     }
 }
 
-struct Compiler<'a, L> {
-    pattern: &'a [ENodeOrVar<L>],
+struct Compiler<L> {
     v2r: VarToReg,
     todo: TodoList<L>,
     out: Reg,
+    instructions: Vec<Instruction<L>>,
 }
 
-impl<'a, L: Language> Compiler<'a, L> {
-    fn compile(pattern: &'a [ENodeOrVar<L>]) -> Program<L> {
-        let last = pattern.last().unwrap();
-        let mut compiler = Self {
-            pattern,
+impl<L: Language> Compiler<L> {
+    fn new() -> Self {
+        Self {
             v2r: Default::default(),
             todo: Default::default(),
             out: Reg(1),
+            instructions: vec![],
+        }
+    }
+
+    fn compile(pattern: &[ENodeOrVar<L>]) -> Program<L> {
+        let mut compiler = Self::new();
+        compiler.add_pattern(pattern, Reg(0));
+        compiler.finish()
+    }
+
+    fn compile_multi(patterns: &[(Var, PatternAst<L>)]) -> Program<L> {
+        let mut compiler = Self::new();
+        for (i, (var, pattern)) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let reg = if i == 0 {
+                Reg(0)
+            } else if let Some(&reg) = compiler.v2r.get(var) {
+                reg
+            } else {
+                compiler.scan(pattern)
+            };
+            compiler.v2r.entry(*var).or_insert(reg);
+            compiler.add_pattern(pattern, reg);
+        }
+        compiler.finish()
+    }
+
+    // binds a fresh register to every eclass that could match the
+    // root of the given pattern
+    fn scan(&mut self, pattern: &[ENodeOrVar<L>]) -> Reg {
+        let out = self.out;
+        self.out.0 += 1;
+        let op = match pattern.last().unwrap() {
+            ENodeOrVar::ENode(node) => Some(node.clone().map_children(|_| Id::from(0))),
+            ENodeOrVar::Var(_) => None,
         };
-        compiler.todo.push(Todo {
-            reg: Reg(0),
-            pat: last.clone(),
-        });
-        compiler.go()
+        self.instructions.push(Instruction::Scan { op, out });
+        out
     }
 
-    fn go(&mut self) -> Program<L> {
-        let mut instructions = vec![];
+    // matches the given pattern against the eclass in register `root`
+    fn add_pattern(&mut self, pattern: &[ENodeOrVar<L>], root: Reg) {
+        self.todo.push(Todo {
+            reg: root,
+            pat: pattern.last().unwrap().clone(),
+        });
+
         while let Some(Todo { reg: i, pat }) = self.todo.pop() {
             match pat {
                 ENodeOrVar::Var(v) => {
                     if let Some(&j) = self.v2r.get(&v) {
-                        instructions.push(Instruction::Compare { i, j })
+                        self.instructions.push(Instruction::Compare { i, j })
                     } else {
                         self.v2r.insert(v, i);
                     }
@@ -181,24 +250,26 @@ impl<'a, L: Language> Compiler<'a, L> {
                         let r = Reg(out.0 + id as u32);
                         self.todo.push(Todo {
                             reg: r,
-                            pat: self.pattern[usize::from(child)].clone(),
+                            pat: pattern[usize::from(child)].clone(),
                         });
                         id += 1;
                     });
 
                     // zero out the children so Bind can use it to sort
                     let node = node.map_children(|_| Id::from(0));
-                    instructions.push(Instruction::Bind { i, node, out })
+                    self.instructions.push(Instruction::Bind { i, node, out })
                 }
             }
         }
+    }
 
+    fn finish(self) -> Program<L> {
         let mut subst = Subst::default();
         for (v, r) in &self.v2r {
             subst.insert(*v, Id::from(r.0 as usize));
         }
         Program {
-            instructions,
+            instructions: self.instructions,
             subst,
         }
     }
@@ -211,6 +282,12 @@ impl<L: Language> Program<L> {
         program
     }
 
+    pub(crate) fn compile_from_multi_pat(patterns: &[(Var, PatternAst<L>)]) -> Self {
+        let program = Compiler::compile_multi(patterns);
+        log::debug!("Compiled {:?} to {:?}", patterns, program);
+        program
+    }
+
     pub fn run<A>(&self, egraph: &EGraph<L, A>, eclass: Id) -> Vec<Subst>
     where
         A: Analysis<L>,
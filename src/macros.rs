@@ -66,7 +66,7 @@ define_language! {
 #[macro_export]
 macro_rules! define_language {
     ($(#[$meta:meta])* $vis:vis enum $name:ident $variants:tt) => {
-        $crate::__define_language!($(#[$meta])* $vis enum $name $variants -> {} {} {} {} {} {});
+        $crate::__define_language!($(#[$meta])* $vis enum $name $variants -> {} {} {} {} {} {} {});
     };
 }
 
@@ -75,7 +75,7 @@ macro_rules! define_language {
 macro_rules! __define_language {
     ($(#[$meta:meta])* $vis:vis enum $name:ident {} ->
      $decl:tt {$($matches:tt)*} $for_each:tt $for_each_mut:tt
-     $display_op:tt {$($from_op_str:tt)*}
+     $display_op:tt {$($from_op_str:tt)*} {$($bad_arity:tt)*}
     ) => {
         $(#[$meta])*
         #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
@@ -102,10 +102,23 @@ macro_rules! __define_language {
                 match self $display_op
             }
 
-            fn from_op_str(op_str: &str, children: Vec<$crate::Id>) -> ::std::result::Result<Self, String> {
+            fn from_op_str(op_str: &str, children: Vec<$crate::Id>) -> ::std::result::Result<Self, $crate::FromOpError> {
                 match (op_str, children) {
                     $($from_op_str)*
-                    (s, c) => Err(::std::format!("Failed to parse '{}' with children {:?}", s, c)),
+                    (s, c) => {
+                        // each variant's operator test and number of children
+                        let arities: &[(fn(&str) -> bool, ::std::option::Option<usize>)] = &[$($bad_arity)*];
+                        let mut err = $crate::FromOpError::new(s, c.len(), "unknown operator");
+                        for &(is_op, arity) in arities {
+                            if is_op(s) {
+                                err.message = "wrong number of children".into();
+                                if let Some(arity) = arity {
+                                    err = err.with_expected_arity(arity);
+                                }
+                            }
+                        }
+                        Err(err)
+                    }
                 }
             }
         }
@@ -117,7 +130,7 @@ macro_rules! __define_language {
          $($variants:tt)*
      } ->
      { $($decl:tt)* } { $($matches:tt)* } { $($for_each:tt)* } { $($for_each_mut:tt)* }
-     { $($display_op:tt)* } { $($from_op_str:tt)* }
+     { $($display_op:tt)* } { $($from_op_str:tt)* } { $($bad_arity:tt)* }
     ) => {
         $crate::__define_language!(
             $(#[$meta])* $vis enum $name
@@ -128,6 +141,7 @@ macro_rules! __define_language {
             { $($for_each_mut)*  $name::$variant => &mut [], }
             { $($display_op)*    $name::$variant => &$string, }
             { $($from_op_str)*   ($string, v) if v.is_empty() => Ok($name::$variant), }
+            { $($bad_arity)*     (|s: &str| s == $string, Some(0)), }
         );
     };
 
@@ -137,7 +151,7 @@ macro_rules! __define_language {
          $($variants:tt)*
      } ->
      { $($decl:tt)* } { $($matches:tt)* } { $($for_each:tt)* } { $($for_each_mut:tt)* }
-     { $($display_op:tt)* } { $($from_op_str:tt)* }
+     { $($display_op:tt)* } { $($from_op_str:tt)* } { $($bad_arity:tt)* }
     ) => {
         $crate::__define_language!(
             $(#[$meta])* $vis enum $name
@@ -151,6 +165,7 @@ macro_rules! __define_language {
                 let ids = <$ids as $crate::LanguageChildren>::from_vec(v);
                 Ok($name::$variant(ids))
             }, }
+            { $($bad_arity)*     (|s: &str| s == $string, <$ids as $crate::LanguageChildren>::fixed_length()), }
         );
     };
 
@@ -160,7 +175,7 @@ macro_rules! __define_language {
          $($variants:tt)*
      } ->
      { $($decl:tt)* } { $($matches:tt)* } { $($for_each:tt)* } { $($for_each_mut:tt)* }
-     { $($display_op:tt)* } { $($from_op_str:tt)* }
+     { $($display_op:tt)* } { $($from_op_str:tt)* } { $($bad_arity:tt)* }
     ) => {
         $crate::__define_language!(
             $(#[$meta])* $vis enum $name
@@ -171,6 +186,7 @@ macro_rules! __define_language {
             { $($for_each_mut)*  $name::$variant(_data) => &mut [], }
             { $($display_op)*    $name::$variant(data) => data, }
             { $($from_op_str)*   (s, v) if s.parse::<$data>().is_ok() && v.is_empty() => Ok($name::$variant(s.parse().unwrap())), }
+            { $($bad_arity)*     (|s: &str| s.parse::<$data>().is_ok(), Some(0)), }
         );
     };
 
@@ -180,7 +196,7 @@ macro_rules! __define_language {
          $($variants:tt)*
      } ->
      { $($decl:tt)* } { $($matches:tt)* } { $($for_each:tt)* } { $($for_each_mut:tt)* }
-     { $($display_op:tt)* } { $($from_op_str:tt)* }
+     { $($display_op:tt)* } { $($from_op_str:tt)* } { $($bad_arity:tt)* }
     ) => {
         $crate::__define_language!(
             $(#[$meta])* $vis enum $name
@@ -195,6 +211,7 @@ macro_rules! __define_language {
                 let ids = <$ids as $crate::LanguageChildren>::from_vec(v);
                 Ok($name::$variant(data, ids))
             }, }
+            { $($bad_arity)*     (|s: &str| s.parse::<$data>().is_ok(), <$ids as $crate::LanguageChildren>::fixed_length()), }
         );
     };
 }
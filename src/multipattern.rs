@@ -0,0 +1,350 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::*;
+
+/// A set of patterns bound to variables, matched or applied jointly.
+///
+/// Where a [`Pattern`] matches a single term rooted in one eclass, a
+/// [`MultiPattern`] matches several terms, possibly in different
+/// eclasses, that agree on their shared variables.
+/// Each pattern is bound to a variable, which gets bound to the
+/// eclass that pattern matched.
+///
+/// You can create a [`MultiPattern`] with [`MultiPattern::new`] or
+/// parse it from a comma-separated list of `?var = pattern` bindings.
+///
+/// As a [`Searcher`], a [`MultiPattern`] is compiled to a single
+/// matching program that finds all substitutions that satisfy every
+/// binding. The returned [`SearchMatches`] are grouped by the eclass
+/// matched by the first pattern.
+/// [`Searcher::search_since`] is not incremental for multipatterns; it
+/// always searches everything.
+///
+/// As an [`Applier`], a [`MultiPattern`] instantiates each pattern in
+/// order. If its variable is already bound (by the searcher or an
+/// earlier binding), the instantiation is unioned with that eclass;
+/// otherwise the variable is bound to the new term for the following
+/// bindings.
+///
+/// # Example
+/// ```
+/// use egg::{*, SymbolLang as S};
+///
+/// let mut egraph = EGraph::<S, ()>::default();
+/// egraph.add_expr(&"(+ a b)".parse().unwrap());
+/// egraph.add_expr(&"(< a c)".parse().unwrap());
+/// egraph.rebuild();
+///
+/// let searcher: MultiPattern<S> = "?s = (+ ?x ?y), ?l = (< ?x ?z)".parse().unwrap();
+/// let applier: MultiPattern<S> = "?r = (< ?s (+ ?z ?y))".parse().unwrap();
+/// let rule = Rewrite::new("shift", searcher, applier).unwrap();
+///
+/// let matches = rule.search(&egraph);
+/// assert_eq!(matches.len(), 1);
+/// rule.apply(&mut egraph, &matches);
+/// egraph.rebuild();
+/// let result: Pattern<S> = "(< (+ a b) (+ c b))".parse().unwrap();
+/// assert_eq!(result.search(&egraph).len(), 1);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultiPattern<L> {
+    asts: Vec<(Var, PatternAst<L>)>,
+    program: machine::Program<L>,
+}
+
+impl<L: Language> MultiPattern<L> {
+    /// Creates a new multipattern, binding each variable to its pattern.
+    ///
+    /// Panics if `asts` is empty.
+    pub fn new(asts: Vec<(Var, PatternAst<L>)>) -> Self {
+        assert!(
+            !asts.is_empty(),
+            "A multipattern needs at least one pattern"
+        );
+        let program = machine::Program::compile_from_multi_pat(&asts);
+        Self { asts, program }
+    }
+
+    /// Returns the variable bindings of this multipattern.
+    pub fn asts(&self) -> &[(Var, PatternAst<L>)] {
+        &self.asts
+    }
+}
+
+/// An error from parsing a [`MultiPattern`] from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultiPatternParseError {
+    /// A binding wasn't of the form `?var = pattern`.
+    BadBinding(String),
+    /// The variable of a binding doesn't start with `?`.
+    BadVar(String),
+    /// The pattern of a binding failed to parse. The error's line and
+    /// column are positions in the whole multipattern.
+    BadPattern(RecExprParseError),
+}
+
+impl fmt::Display for MultiPatternParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultiPatternParseError::BadBinding(binding) => write!(
+                f,
+                "expected a binding like '?var = pattern' in multipattern, found '{}'",
+                binding
+            ),
+            MultiPatternParseError::BadVar(var) => {
+                write!(f, "multipattern variable '{}' doesn't start with '?'", var)
+            }
+            MultiPatternParseError::BadPattern(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MultiPatternParseError {}
+
+// splits on commas that are not inside parentheses, returning each part
+// with its byte offset
+fn split_top_level(s: &str) -> Vec<(usize, &str)> {
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut parts = vec![];
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push((start, &s[start..i]));
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push((start, &s[start..]));
+    parts
+}
+
+// moves an error in a pattern that starts after `before` to its
+// position in the whole input
+fn relocate(mut err: RecExprParseError, before: &str) -> RecExprParseError {
+    if err.line == 1 {
+        err.column += before.rsplit('\n').next().unwrap().chars().count();
+    }
+    err.line += before.matches('\n').count();
+    err
+}
+
+impl<L: Language> FromStr for MultiPattern<L> {
+    type Err = MultiPatternParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut asts = vec![];
+        for (start, binding) in split_top_level(s) {
+            let eq = binding
+                .find('=')
+                .ok_or_else(|| MultiPatternParseError::BadBinding(binding.trim().to_string()))?;
+            let var = binding[..eq].trim();
+            let var: Var = var
+                .parse()
+                .map_err(|_| MultiPatternParseError::BadVar(var.to_string()))?;
+            let pattern = &binding[eq + 1..];
+            let pattern_start = start + eq + 1 + pattern.len() - pattern.trim_start().len();
+            let pattern: PatternAst<L> = pattern.trim().parse().map_err(|err| {
+                MultiPatternParseError::BadPattern(relocate(err, &s[..pattern_start]))
+            })?;
+            asts.push((var, pattern));
+        }
+        Ok(MultiPattern::new(asts))
+    }
+}
+
+impl<L: Language> fmt::Display for MultiPattern<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (var, ast)) in self.asts.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} = {}", var, ast)?;
+        }
+        Ok(())
+    }
+}
+
+impl<L: Language, A: Analysis<L>> Searcher<L, A> for MultiPattern<L> {
+    fn search(&self, egraph: &EGraph<L, A>) -> Vec<SearchMatches> {
+        let (_, first) = &self.asts[0];
+        let root = first.as_ref().last().unwrap();
+        pattern::search_candidates(egraph, root, None, &self.program)
+    }
+
+    fn search_eclass(&self, egraph: &EGraph<L, A>, eclass: Id) -> Option<SearchMatches> {
+        let substs = self.program.run(egraph, eclass);
+        if substs.is_empty() {
+            None
+        } else {
+            Some(SearchMatches { eclass, substs })
+        }
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        let mut vars = vec![];
+        for (var, ast) in &self.asts {
+            if !vars.contains(var) {
+                vars.push(*var);
+            }
+            for n in ast.as_ref() {
+                if let ENodeOrVar::Var(v) = n {
+                    if !vars.contains(v) {
+                        vars.push(*v)
+                    }
+                }
+            }
+        }
+        vars
+    }
+}
+
+impl<L: Language> MultiPattern<L> {
+    // binds or unions each pattern for one substitution, returning the
+    // eclasses where a union did something
+    fn apply_subst<A: Analysis<L>>(&self, egraph: &mut EGraph<L, A>, subst: &Subst) -> Vec<Id> {
+        let mut added = vec![];
+        let mut id_buf: Vec<Id> = vec![];
+        let mut subst = subst.clone();
+        for (var, ast) in &self.asts {
+            let ast = ast.as_ref();
+            id_buf.resize(ast.len(), 0.into());
+            let id = pattern::apply_pat(&mut id_buf, ast, egraph, &subst);
+            if let Some(&bound) = subst.get(*var) {
+                let (to, did_something) = egraph.union_match(bound, id, &subst, false);
+                if did_something {
+                    added.push(to)
+                }
+            } else {
+                subst.insert(*var, id);
+            }
+        }
+        added
+    }
+}
+
+impl<L: Language, A: Analysis<L>> Applier<L, A> for MultiPattern<L> {
+    /// Binds or unions each pattern for this one substitution.
+    ///
+    /// The unions happen here, and none of them are with `eclass`, so
+    /// this always returns an empty list.
+    fn apply_one(&self, egraph: &mut EGraph<L, A>, _eclass: Id, subst: &Subst) -> Vec<Id> {
+        self.apply_subst(egraph, subst);
+        vec![]
+    }
+
+    fn apply_matches(&self, egraph: &mut EGraph<L, A>, matches: &[SearchMatches]) -> Vec<Id> {
+        let mut added = vec![];
+        for mat in matches {
+            for subst in &mat.substs {
+                added.extend(self.apply_subst(egraph, subst));
+            }
+        }
+        added
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        // variables bound by earlier bindings don't need to come from the searcher
+        let mut bound = vec![];
+        let mut vars = vec![];
+        for (var, ast) in &self.asts {
+            for n in ast.as_ref() {
+                if let ENodeOrVar::Var(v) = n {
+                    if !bound.contains(v) && !vars.contains(v) {
+                        vars.push(*v)
+                    }
+                }
+            }
+            bound.push(*var);
+        }
+        vars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    type EGraph = crate::EGraph<S, ()>;
+
+    #[test]
+    fn simple_multipattern() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        let fab = egraph.add_expr(&"(f a b)".parse().unwrap());
+        let gac = egraph.add_expr(&"(g a c)".parse().unwrap());
+        egraph.add_expr(&"(g b c)".parse().unwrap());
+        egraph.rebuild();
+
+        let searcher: MultiPattern<S> = "?x = (f ?a ?b), ?y = (g ?a ?c)".parse().unwrap();
+        assert_eq!(searcher.to_string(), "?x = (f ?a ?b), ?y = (g ?a ?c)");
+
+        let union: MultiPattern<S> = "?x = ?y".parse().unwrap();
+        let rule = Rewrite::new("join", searcher.clone(), union).unwrap();
+        let matches = rule.search(&egraph);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].substs.len(), 1);
+        assert_eq!(matches[0].substs[0]["?y".parse::<Var>().unwrap()], gac);
+
+        let applied = rule.apply(&mut egraph, &matches);
+        egraph.rebuild();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(egraph.find(fab), egraph.find(gac));
+
+        let add: MultiPattern<S> = "?z = (h ?b ?c), ?w = (k ?z)".parse().unwrap();
+        let rule = Rewrite::new("add", searcher, add).unwrap();
+        let matches = rule.search(&egraph);
+        rule.apply(&mut egraph, &matches);
+        egraph.rebuild();
+        let result: Pattern<S> = "(k (h b c))".parse().unwrap();
+        assert_eq!(result.search(&egraph).len(), 1);
+    }
+
+    #[test]
+    fn conditional_multipattern() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        let fab = egraph.add_expr(&"(f a b)".parse().unwrap());
+        let gac = egraph.add_expr(&"(g a c)".parse().unwrap());
+        let fbb = egraph.add_expr(&"(f b b)".parse().unwrap());
+        let gbc = egraph.add_expr(&"(g b c)".parse().unwrap());
+        egraph.rebuild();
+
+        let searcher: MultiPattern<S> = "?x = (f ?a ?b), ?y = (g ?a ?c)".parse().unwrap();
+        let union: MultiPattern<S> = "?x = ?y".parse().unwrap();
+        let a: Var = "?a".parse().unwrap();
+        let is_a = move |egraph: &mut EGraph, _: Id, subst: &Subst| {
+            egraph[subst[a]].nodes[0] == S::leaf("a")
+        };
+        let rule: Rewrite<S, ()> = rewrite!("join-a"; {searcher} => {union} if is_a);
+        let matches = rule.search(&egraph);
+        assert_eq!(matches.len(), 2);
+
+        let applied = rule.apply(&mut egraph, &matches);
+        egraph.rebuild();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(egraph.find(fab), egraph.find(gac));
+        assert_ne!(egraph.find(fbb), egraph.find(gbc));
+    }
+
+    #[test]
+    fn bad_multipatterns() {
+        use MultiPatternParseError::*;
+        let parse_err = |s: &str| s.parse::<MultiPattern<S>>().unwrap_err();
+
+        assert_eq!(parse_err("?x (f ?a)"), BadBinding("?x (f ?a)".into()));
+        assert_eq!(parse_err("x = (f ?a)"), BadVar("x".into()));
+        assert_eq!(parse_err("?x = (f ?a), ?y"), BadBinding("?y".into()));
+        match parse_err("?x = (f ?a),\n  ?y = (g ?a))") {
+            BadPattern(err) => {
+                assert_eq!(err.kind, RecExprParseErrorKind::TrailingInput);
+                assert_eq!((err.line, err.column), (2, 14));
+            }
+            err => panic!("unexpected error {}", err),
+        }
+    }
+}
@@ -117,18 +117,19 @@ impl<L: Language> Language for ENodeOrVar<L> {
         }
     }
 
-    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, String> {
+    fn from_op_str(op_str: &str, children: Vec<Id>) -> Result<Self, FromOpError> {
         if op_str.starts_with('?') && op_str.len() > 1 {
             if children.is_empty() {
-                op_str
-                    .parse()
-                    .map(ENodeOrVar::Var)
-                    .map_err(|err| format!("Failed to parse var: {}", err))
+                op_str.parse().map(ENodeOrVar::Var).map_err(|err| {
+                    FromOpError::new(op_str, 0, format!("Failed to parse var: {}", err))
+                })
             } else {
-                Err(format!(
-                    "Tried to parse pattern variable '{}' in the op position",
-                    op_str
-                ))
+                Err(FromOpError::new(
+                    op_str,
+                    children.len(),
+                    "pattern variable in the op position",
+                )
+                .with_expected_arity(0))
             }
         } else {
             L::from_op_str(op_str, children).map(ENodeOrVar::ENode)
@@ -144,7 +145,7 @@ impl<L: Language> Language for ENodeOrVar<L> {
 }
 
 impl<L: Language> std::str::FromStr for Pattern<L> {
-    type Err = String;
+    type Err = RecExprParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         PatternAst::from_str(s).map(Self::from)
     }
@@ -185,6 +186,27 @@ impl<L: Language> fmt::Display for Pattern<L> {
     }
 }
 
+#[cfg(feature = "serde-1")]
+impl<L: Language> serde::Serialize for Pattern<L> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.ast, serializer)
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de, L: Language> serde::Deserialize<'de> for Pattern<L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let ast: PatternAst<L> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self::from(ast))
+    }
+}
+
 /// The result of searching a [`Searcher`] over one eclass.
 ///
 /// Note that one [`SearchMatches`] can contain many found
@@ -202,23 +224,18 @@ pub struct SearchMatches {
 
 impl<L: Language, A: Analysis<L>> Searcher<L, A> for Pattern<L> {
     fn search(&self, egraph: &EGraph<L, A>) -> Vec<SearchMatches> {
-        match self.ast.as_ref().last().unwrap() {
-            ENodeOrVar::ENode(e) => {
-                #[allow(clippy::mem_discriminant_non_enum)]
-                let key = std::mem::discriminant(e);
-                match egraph.classes_by_op.get(&key) {
-                    None => vec![],
-                    Some(ids) => ids
-                        .iter()
-                        .filter_map(|&id| self.search_eclass(egraph, id))
-                        .collect(),
-                }
-            }
-            ENodeOrVar::Var(_) => egraph
-                .classes()
-                .filter_map(|e| self.search_eclass(egraph, e.id))
-                .collect(),
+        let root = self.ast.as_ref().last().unwrap();
+        search_candidates(egraph, root, None, &self.program)
+    }
+
+    fn search_since(&self, egraph: &EGraph<L, A>, timestamp: usize) -> Vec<SearchMatches> {
+        if timestamp == 0 {
+            return self.search(egraph);
         }
+        let ast = self.ast.as_ref();
+        let touched = egraph.classes_touched_since(timestamp, height(ast) - 1);
+        let root = ast.last().unwrap();
+        search_candidates(egraph, root, Some(&touched), &self.program)
     }
 
     fn search_eclass(&self, egraph: &EGraph<L, A>, eclass: Id) -> Option<SearchMatches> {
@@ -230,11 +247,148 @@ impl<L: Language, A: Analysis<L>> Searcher<L, A> for Pattern<L> {
         }
     }
 
+    fn get_pattern_ast(&self) -> Option<&PatternAst<L>> {
+        Some(&self.ast)
+    }
+
     fn vars(&self) -> Vec<Var> {
         Pattern::vars(self)
     }
 }
 
+/// Returns the number of levels in a pattern, counting variables as a
+/// level of their own.
+fn height<L: Language>(ast: &[ENodeOrVar<L>]) -> usize {
+    let mut heights = vec![1; ast.len()];
+    for (i, node) in ast.iter().enumerate() {
+        if let ENodeOrVar::ENode(node) = node {
+            let max_child = node.fold(0, |max, child| max.max(heights[usize::from(child)]));
+            heights[i] = max_child + 1;
+        }
+    }
+    heights[ast.len() - 1]
+}
+
+/// Runs `program` on every eclass that could match a pattern with the
+/// given root, keeping the results in eclass order.
+/// If `only` is given, only the eclasses in that sorted list are searched.
+///
+/// If the egraph was made with
+/// [`with_parallel_search`](EGraph::with_parallel_search()), the
+/// eclasses are searched concurrently.
+/// Once a [`Runner`] running on the egraph is stopped through its
+/// [`StopHandle`], the eclasses not searched yet are skipped.
+pub(crate) fn search_candidates<L, A>(
+    egraph: &EGraph<L, A>,
+    root: &ENodeOrVar<L>,
+    only: Option<&[Id]>,
+    program: &machine::Program<L>,
+) -> Vec<SearchMatches>
+where
+    L: Language,
+    A: Analysis<L>,
+{
+    match (root, only) {
+        (ENodeOrVar::ENode(e), _) => {
+            #[allow(clippy::mem_discriminant_non_enum)]
+            let key = std::mem::discriminant(e);
+            match egraph.classes_by_op.get(&key) {
+                None => vec![],
+                Some(ids) => match only {
+                    Some(only) => search_ids(
+                        egraph,
+                        program,
+                        only.iter().copied().filter(|id| ids.contains(id)),
+                    ),
+                    None => search_ids(egraph, program, ids.iter().copied()),
+                },
+            }
+        }
+        (ENodeOrVar::Var(_), Some(only)) => search_ids(egraph, program, only.iter().copied()),
+        (ENodeOrVar::Var(_), None) => search_ids(egraph, program, egraph.classes().map(|e| e.id)),
+    }
+}
+
+/// Runs `program` on the given eclasses for [`search_candidates`].
+/// The ids are only collected when they have to be sorted or handed
+/// to the parallel search.
+fn search_ids<L, A>(
+    egraph: &EGraph<L, A>,
+    program: &machine::Program<L>,
+    ids: impl Iterator<Item = Id>,
+) -> Vec<SearchMatches>
+where
+    L: Language,
+    A: Analysis<L>,
+{
+    #[cfg(feature = "parallel")]
+    {
+        if let Some(par_search) = egraph.par_search {
+            let mut ids: Vec<Id> = ids.collect();
+            egraph.order_ids(&mut ids);
+            return par_search(egraph, program, ids);
+        }
+    }
+
+    if egraph.is_deterministic() {
+        let mut ids: Vec<Id> = ids.collect();
+        egraph.order_ids(&mut ids);
+        return ids
+            .into_iter()
+            .filter_map(|id| search_one(egraph, program, id))
+            .collect();
+    }
+
+    ids.filter_map(|id| search_one(egraph, program, id))
+        .collect()
+}
+
+/// The parallel part of [`search_candidates`], which
+/// [`EGraph::with_parallel_search`] stores in the egraph, since only
+/// there are the egraph and its data known to be [`Sync`].
+#[cfg(feature = "parallel")]
+pub(crate) type ParSearch<L, A> =
+    fn(&EGraph<L, A>, &machine::Program<L>, Vec<Id>) -> Vec<SearchMatches>;
+
+/// See [`ParSearch`].
+#[cfg(feature = "parallel")]
+pub(crate) fn par_search<L, A>(
+    egraph: &EGraph<L, A>,
+    program: &machine::Program<L>,
+    ids: Vec<Id>,
+) -> Vec<SearchMatches>
+where
+    L: Language + Send + Sync,
+    A: Analysis<L> + Sync,
+    A::Data: Sync,
+{
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    ids.into_par_iter()
+        .filter_map(|id| search_one(egraph, program, id))
+        .collect()
+}
+
+fn search_one<L, A>(
+    egraph: &EGraph<L, A>,
+    program: &machine::Program<L>,
+    eclass: Id,
+) -> Option<SearchMatches>
+where
+    L: Language,
+    A: Analysis<L>,
+{
+    // a stopped runner throws the matches away, so skip the rest
+    if egraph.is_search_stopped() {
+        return None;
+    }
+    let substs = program.run(egraph, eclass);
+    if substs.is_empty() {
+        None
+    } else {
+        Some(SearchMatches { eclass, substs })
+    }
+}
+
 impl<L, A> Applier<L, A> for Pattern<L>
 where
     L: Language,
@@ -247,6 +401,10 @@ where
         vec![id]
     }
 
+    fn get_pattern_ast(&self) -> Option<&PatternAst<L>> {
+        Some(&self.ast)
+    }
+
     fn vars(&self) -> Vec<Var> {
         Pattern::vars(self)
     }
@@ -258,7 +416,7 @@ where
         for mat in matches {
             for subst in &mat.substs {
                 let id = apply_pat(&mut id_buf, ast, egraph, subst);
-                let (to, did_something) = egraph.union(id, mat.eclass);
+                let (to, did_something) = egraph.union_match(mat.eclass, id, subst, true);
                 if did_something {
                     added.push(to)
                 }
@@ -268,7 +426,7 @@ where
     }
 }
 
-fn apply_pat<L: Language, A: Analysis<L>>(
+pub(crate) fn apply_pat<L: Language, A: Analysis<L>>(
     ids: &mut [Id],
     pat: &[ENodeOrVar<L>],
     egraph: &mut EGraph<L, A>,
@@ -283,7 +441,7 @@ fn apply_pat<L: Language, A: Analysis<L>>(
             ENodeOrVar::ENode(e) => {
                 let n = e.clone().map_children(|child| ids[usize::from(child)]);
                 trace!("adding: {:?}", n);
-                egraph.add(n)
+                egraph.add_uncanonical(n)
             }
         };
         ids[i] = id;
@@ -343,4 +501,132 @@ mod tests {
         let (_, best) = ext.find_best(plus);
         eprintln!("Best: {:#?}", best);
     }
+
+    #[test]
+    fn search_since() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        egraph.add_expr(&"(f (g a) (g b))".parse().unwrap());
+        egraph.add_expr(&"(f (g c) d)".parse().unwrap());
+        egraph.rebuild();
+
+        let pat: Pattern<S> = "(f (g ?x) ?y)".parse().unwrap();
+        assert_eq!(pat.search_since(&egraph, 0).len(), 2);
+
+        let timestamp = egraph.timestamp();
+        assert!(pat.search_since(&egraph, timestamp).is_empty());
+
+        // a new leaf makes a new match two levels up
+        let e = egraph.add(S::leaf("e"));
+        let d = egraph.add(S::leaf("d"));
+        egraph.union(d, e);
+        egraph.rebuild();
+        let matches = pat.search_since(&egraph, timestamp);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].substs[0]["?y".parse::<Var>().unwrap()],
+            egraph.find(d)
+        );
+
+        // variable-only patterns just search the changed eclasses
+        let timestamp = egraph.timestamp();
+        let h = egraph.add_expr(&"(h a)".parse().unwrap());
+        egraph.rebuild();
+        let var: Pattern<S> = "?z".parse().unwrap();
+        let matches = var.search_since(&egraph, timestamp);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].eclass, h);
+    }
+
+    #[test]
+    fn search_stopped() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        egraph.add_expr(&"(f (g a) (g b))".parse().unwrap());
+        egraph.rebuild();
+
+        let pat: Pattern<S> = "(g ?x)".parse().unwrap();
+        let handle = StopHandle::default();
+        egraph.set_stop_handle(Some(handle.clone()));
+        assert_eq!(pat.search(&egraph).len(), 2);
+
+        handle.stop();
+        assert!(pat.search(&egraph).is_empty());
+        assert!(pat.search_since(&egraph, 1).is_empty());
+    }
+
+    #[cfg(feature = "serde-1")]
+    #[test]
+    fn serde_roundtrip() {
+        define_language! {
+            enum Math {
+                Num(i32),
+                "+" = Add([Id; 2]),
+            }
+        }
+
+        let expr: RecExpr<Math> = "(+ 1 (+ 2 3))".parse().unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+        assert_eq!(json, "\"(+ 1 (+ 2 3))\"");
+        assert_eq!(serde_json::from_str::<RecExpr<Math>>(&json).unwrap(), expr);
+
+        let pattern: Pattern<Math> = "(+ ?a (+ 2 ?b))".parse().unwrap();
+        let json = serde_json::to_string(&pattern).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Pattern<Math>>(&json).unwrap(),
+            pattern
+        );
+
+        let err = serde_json::from_str::<RecExpr<Math>>("\"(+ 1)\"").unwrap_err();
+        assert!(err.to_string().contains("(+ 1)"), "bad error: {}", err);
+
+        let rules: Vec<Rewrite<Math, ()>> = vec![
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("zero"; "(+ ?a 0)" => "?a"),
+        ];
+        let json = serde_json::to_string(&rules).unwrap();
+        let loaded: Vec<Rewrite<Math, ()>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (rw, loaded) in rules.iter().zip(&loaded) {
+            assert_eq!(rw.name(), loaded.name());
+            assert_eq!(
+                rw.searcher.get_pattern_ast(),
+                loaded.searcher.get_pattern_ast()
+            );
+            assert_eq!(
+                rw.applier.get_pattern_ast(),
+                loaded.applier.get_pattern_ast()
+            );
+        }
+
+        let unbound = r#"{"name": "bad", "searcher": "(+ ?a 1)", "applier": "?b"}"#;
+        assert!(serde_json::from_str::<Rewrite<Math, ()>>(unbound).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_search() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        for i in 0..50 {
+            let expr = format!("(+ (* x{} 2) (+ y (* x{} 2)))", i, i % 7);
+            egraph.add_expr(&expr.parse().unwrap());
+        }
+        egraph.rebuild();
+        let patterns: Vec<Pattern<S>> = vec![
+            "(+ ?a ?b)".parse().unwrap(),
+            "(+ (* ?x 2) (+ ?y (* ?z 2)))".parse().unwrap(),
+            "?a".parse().unwrap(),
+        ];
+
+        let parallel = egraph.clone().with_parallel_search();
+        let flatten = |matches: Vec<SearchMatches>| -> Vec<(Id, Vec<Subst>)> {
+            matches.into_iter().map(|m| (m.eclass, m.substs)).collect()
+        };
+        for pattern in &patterns {
+            let expected = flatten(pattern.search(&egraph));
+            assert!(!expected.is_empty());
+            assert_eq!(flatten(pattern.search(&parallel)), expected);
+        }
+    }
 }
@@ -12,6 +12,12 @@ use crate::*;
 /// It additionally stores a name used to refer to the rewrite and a
 /// long name used for debugging.
 ///
+/// If the `serde-1` feature is enabled, a [`Rewrite`] whose searcher
+/// and applier are both patterns (see
+/// [`Searcher::get_pattern_ast`] and [`Applier::get_pattern_ast`]) can be
+/// serialized as a `name`, `searcher` and `applier`, and deserialized
+/// back into a [`Rewrite`] between [`Pattern`]s.
+///
 #[derive(Clone)]
 #[non_exhaustive]
 pub struct Rewrite<L, N> {
@@ -48,6 +54,54 @@ where
     }
 }
 
+#[cfg(feature = "serde-1")]
+impl<L: Language, N: Analysis<L>> serde::Serialize for Rewrite<L, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error, SerializeStruct};
+        let searcher = self.searcher.get_pattern_ast();
+        let applier = self.applier.get_pattern_ast();
+        match (searcher, applier) {
+            (Some(searcher), Some(applier)) => {
+                let mut s = serializer.serialize_struct("Rewrite", 3)?;
+                s.serialize_field("name", &self.name)?;
+                s.serialize_field("searcher", searcher)?;
+                s.serialize_field("applier", applier)?;
+                s.end()
+            }
+            _ => Err(S::Error::custom(format!(
+                "Rewrite {} can't be serialized, only pattern rewrites can",
+                self.name
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de, L, N> serde::Deserialize<'de> for Rewrite<L, N>
+where
+    L: Language + 'static,
+    N: Analysis<L> + 'static,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(bound(deserialize = "L: Language"))]
+        struct PatternRewrite<L> {
+            name: String,
+            searcher: Pattern<L>,
+            applier: Pattern<L>,
+        }
+
+        let rw = PatternRewrite::<L>::deserialize(deserializer)?;
+        Rewrite::new(rw.name, rw.searcher, rw.applier).map_err(serde::de::Error::custom)
+    }
+}
+
 impl<L, N> Rewrite<L, N> {
     /// Returns the name of the rewrite.
     pub fn name(&self) -> &str {
@@ -89,11 +143,32 @@ impl<L: Language, N: Analysis<L>> Rewrite<L, N> {
         self.searcher.search(egraph)
     }
 
+    /// Call [`search_since`] on the [`Searcher`].
+    ///
+    /// [`search_since`]: Searcher::search_since()
+    pub fn search_since(&self, egraph: &EGraph<L, N>, timestamp: usize) -> Vec<SearchMatches> {
+        self.searcher.search_since(egraph, timestamp)
+    }
+
     /// Call [`apply_matches`] on the [`Applier`].
     ///
+    /// When explanations are enabled, the unions the [`Applier`] makes
+    /// are justified by this rewrite (see [`Justification::Rule`]).
+    ///
     /// [`apply_matches`]: Applier::apply_matches()
     pub fn apply(&self, egraph: &mut EGraph<L, N>, matches: &[SearchMatches]) -> Vec<Id> {
-        self.applier.apply_matches(egraph, matches)
+        if !egraph.are_explanations_enabled() {
+            return self.applier.apply_matches(egraph, matches);
+        }
+
+        let rule = RuleContext {
+            name: self.name.as_str().into(),
+            searcher_ast: self.searcher.get_pattern_ast().cloned(),
+        };
+        let outer = egraph.set_rule_context(Some(rule));
+        let ids = self.applier.apply_matches(egraph, matches);
+        egraph.set_rule_context(outer);
+        ids
     }
 
     /// This `run` is for testing use only. You should use things
@@ -137,16 +212,46 @@ where
 
     /// Search the whole [`EGraph`], returning a list of all the
     /// [`SearchMatches`] where something was found.
-    /// This just calls [`search_eclass`] on each eclass.
+    /// This just calls [`search_eclass`] on each eclass, stopping early
+    /// if a [`Runner`] searching the egraph is stopped through its
+    /// [`StopHandle`].
     ///
     /// [`search_eclass`]: Searcher::search_eclass
     fn search(&self, egraph: &EGraph<L, N>) -> Vec<SearchMatches> {
         egraph
             .classes()
+            .take_while(|_| !egraph.is_search_stopped())
             .filter_map(|e| self.search_eclass(egraph, e.id))
             .collect()
     }
 
+    /// Search the [`EGraph`] for matches that may be new since the
+    /// given [`timestamp`](EGraph::timestamp()).
+    ///
+    /// This must return at least every match that touches an eclass
+    /// changed after `timestamp`, but it may return more.
+    /// A `timestamp` of 0 means everything is new.
+    /// [`Pattern`] only searches the eclasses from
+    /// [`EGraph::classes_touched_since`], which is much faster when
+    /// little has changed.
+    /// The default implementation just calls [`search`].
+    ///
+    /// [`search`]: Searcher::search
+    fn search_since(&self, egraph: &EGraph<L, N>, timestamp: usize) -> Vec<SearchMatches> {
+        let _ = timestamp;
+        self.search(egraph)
+    }
+
+    /// Returns the pattern this Searcher matches, if it has one.
+    ///
+    /// This lets [`Applier`]s record exactly which term was rewritten
+    /// when explanations are enabled.
+    /// By default this returns `None`, in which case explanations fall
+    /// back to the matched eclass.
+    fn get_pattern_ast(&self) -> Option<&PatternAst<L>> {
+        None
+    }
+
     /// Returns a list of the variables bound by this Searcher
     fn vars(&self) -> Vec<Var>;
 }
@@ -263,24 +368,23 @@ where
     ///
     /// The default implementation does this and should suffice for
     /// most use cases.
+    /// When explanations are enabled, its unions are justified by the
+    /// [`Rewrite`] being applied, with the term matched by the
+    /// [`Searcher`] (see [`Searcher::get_pattern_ast`]) if it has a
+    /// pattern.
     ///
     /// [`apply_one`]: Applier::apply_one()
     fn apply_matches(&self, egraph: &mut EGraph<L, N>, matches: &[SearchMatches]) -> Vec<Id> {
         let mut added = vec![];
         for mat in matches {
             for subst in &mat.substs {
-                let ids = self
-                    .apply_one(egraph, mat.eclass, subst)
-                    .into_iter()
-                    .filter_map(|id| {
-                        let (to, did_something) = egraph.union(id, mat.eclass);
-                        if did_something {
-                            Some(to)
-                        } else {
-                            None
-                        }
-                    });
-                added.extend(ids)
+                let ids = self.apply_one(egraph, mat.eclass, subst);
+                for id in ids {
+                    let (to, did_something) = egraph.union_match(mat.eclass, id, subst, true);
+                    if did_something {
+                        added.push(to)
+                    }
+                }
             }
         }
         added
@@ -309,13 +413,21 @@ where
     fn vars(&self) -> Vec<Var> {
         vec![]
     }
+
+    /// Returns the pattern this Applier instantiates, if it has one.
+    ///
+    /// By default this returns `None`.
+    fn get_pattern_ast(&self) -> Option<&PatternAst<L>> {
+        None
+    }
 }
 
 /// An [`Applier`] that checks a [`Condition`] before applying.
 ///
 /// A [`ConditionalApplier`] simply calls [`check`] on the
-/// [`Condition`] before calling [`apply_one`] on the inner
-/// [`Applier`].
+/// [`Condition`] before applying each match with the inner
+/// [`Applier`], so it works with appliers like [`MultiPattern`] that
+/// do their own unions.
 ///
 /// See the [`rewrite!`] macro documentation for an example.
 ///
@@ -349,6 +461,29 @@ where
         }
     }
 
+    fn apply_matches(&self, egraph: &mut EGraph<L, N>, matches: &[SearchMatches]) -> Vec<Id> {
+        // let the inner applier do the unions for each match that
+        // passes, since it may not union with the matched eclass.
+        // Each match is checked right before it is applied, since
+        // applying the ones before it may change the outcome.
+        let mut added = vec![];
+        for mat in matches {
+            let mut single = SearchMatches {
+                eclass: mat.eclass,
+                substs: Vec::with_capacity(1),
+            };
+            for subst in &mat.substs {
+                if self.condition.check(egraph, mat.eclass, subst) {
+                    single.substs.clear();
+                    single.substs.push(subst.clone());
+                    let single = std::slice::from_ref(&single);
+                    added.extend(self.applier.apply_matches(egraph, single));
+                }
+            }
+        }
+        added
+    }
+
     fn vars(&self) -> Vec<Var> {
         let mut vars = self.applier.vars();
         vars.extend(self.condition.vars());
@@ -427,7 +562,7 @@ where
         let a2 = self.1.apply_one(egraph, eclass, subst);
         assert_eq!(a1.len(), 1);
         assert_eq!(a2.len(), 1);
-        a1[0] == a2[0]
+        egraph.find(a1[0]) == egraph.find(a2[0])
     }
 
     fn vars(&self) -> Vec<Var> {
@@ -479,6 +614,28 @@ mod tests {
         assert_eq!(apps, vec![egraph.find(mul)]);
     }
 
+    #[test]
+    fn conditional_checks_before_each_apply() {
+        crate::init_logger();
+        let mut egraph = EGraph::default();
+        let fa = egraph.add_expr(&"(f a)".parse().unwrap());
+        let fb = egraph.add_expr(&"(f b)".parse().unwrap());
+        egraph.union(fa, fb);
+        egraph.rebuild();
+
+        // the condition fails once an earlier match of the same eclass
+        // has applied
+        let no_g = |egraph: &mut EGraph, eclass: Id, _: &Subst| {
+            !egraph[eclass].nodes.iter().any(|n| n.op.as_str() == "g")
+        };
+        let f_to_g = rewrite!("f_to_g"; "(f ?x)" => "(g ?x)" if no_g);
+
+        let apps = f_to_g.run(&mut egraph);
+        assert_eq!(apps.len(), 1);
+        let gs = egraph[fa].nodes.iter().filter(|n| n.op.as_str() == "g");
+        assert_eq!(gs.count(), 1);
+    }
+
     #[test]
     fn fn_rewrite() {
         crate::init_logger();
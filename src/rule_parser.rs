@@ -0,0 +1,504 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::util::HashMap;
+use crate::*;
+
+type ConditionMaker<L, N> = dyn Fn(&[&str]) -> Result<DynCondition<L, N>, String>;
+type ApplierMaker<L, N> = dyn Fn(&[&str]) -> Result<DynApplier<L, N>, String>;
+
+/** Parses [`Rewrite`]s from a text format, so rule sets can change
+without recompiling.
+
+Each rule has a name, a left-hand side, an arrow, and a right-hand side,
+optionally followed by conditions:
+```text
+# lines starting with '#' are comments
+comm-add: (+ ?a ?b) => (+ ?b ?a)
+assoc-add: (+ ?a (+ ?b ?c)) <=> (+ (+ ?a ?b) ?c)
+cancel-div: (/ ?a ?a) => 1 if is_not_zero ?a
+fold-add: (+ ?a ?b) => @fold ?a ?b
+```
+
+- `=>` makes one rewrite, `<=>` makes two, the second named with a
+  `-rev` suffix, just like the [`rewrite!`] macro.
+- Each side is a [`Pattern`]. Instead of a pattern, the right-hand side
+  may be `@name args...`, which calls the applier registered with
+  [`with_applier`](RuleParser::with_applier()).
+- Each `if name args...` calls the condition registered with
+  [`with_condition`](RuleParser::with_condition()). Several conditions
+  can be chained; they are checked in order, as with [`rewrite!`].
+- A rule may span several lines. It goes on until it has an arrow with
+  something on both sides and balanced parentheses, and a line starting
+  with `=>`, `<=>` or `if` always continues the rule before it.
+  Outside of parentheses, a line starting with `name:` always starts a
+  new rule.
+
+The registered functions receive the whitespace-separated arguments
+written after the name, and return the [`Condition`] or [`Applier`]
+to use, or an error message.
+
+# Example
+```
+use egg::{*, SymbolLang as S};
+
+let parser = RuleParser::<S, ()>::new().with_condition("is_not_zero", |args: &[&str]| {
+    let var: Var = args.first().ok_or("is_not_zero takes a variable")?.parse()?;
+    let zero = S::leaf("0");
+    Ok(move |egraph: &mut EGraph<S, ()>, _: Id, subst: &Subst| {
+        !egraph[subst[var]].nodes.contains(&zero)
+    })
+});
+
+let rules = parser
+    .parse(
+        "
+        ## some arithmetic
+        comm-mul: (* ?a ?b) => (* ?b ?a)
+        cancel-div: (/ (* ?a ?b) ?b)
+                    => ?a
+                    if is_not_zero ?b
+        ",
+    )
+    .unwrap();
+assert_eq!(rules.len(), 2);
+
+let start = "(/ (* y x) x)".parse().unwrap();
+let runner = Runner::default().with_expr(&start).run(&rules);
+let y = runner.egraph.lookup(S::leaf("y")).unwrap();
+assert_eq!(runner.egraph.find(runner.roots[0]), y);
+```
+**/
+pub struct RuleParser<L, N> {
+    conditions: HashMap<String, Box<ConditionMaker<L, N>>>,
+    appliers: HashMap<String, Box<ApplierMaker<L, N>>>,
+}
+
+impl<L, N> Default for RuleParser<L, N>
+where
+    L: Language + 'static,
+    N: Analysis<L> + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L, N> fmt::Debug for RuleParser<L, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuleParser")
+            .field("conditions", &self.conditions.keys().collect::<Vec<_>>())
+            .field("appliers", &self.appliers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<L, N> RuleParser<L, N>
+where
+    L: Language + 'static,
+    N: Analysis<L> + 'static,
+{
+    /// Creates a new [`RuleParser`] with no conditions or appliers.
+    pub fn new() -> Self {
+        Self {
+            conditions: Default::default(),
+            appliers: Default::default(),
+        }
+    }
+
+    /// Registers a condition that rules can use as `if name args...`.
+    ///
+    /// `make` is called once per use of the condition, with the
+    /// arguments written after its name.
+    pub fn with_condition<C, F>(mut self, name: impl Into<String>, make: F) -> Self
+    where
+        C: Condition<L, N> + 'static,
+        F: Fn(&[&str]) -> Result<C, String> + 'static,
+    {
+        let make = move |args: &[&str]| make(args).map(|c| DynCondition(Arc::new(c)));
+        self.conditions.insert(name.into(), Box::new(make));
+        self
+    }
+
+    /// Registers an applier that rules can use as the right-hand side
+    /// `@name args...`.
+    ///
+    /// `make` is called once per use of the applier, with the
+    /// arguments written after its name.
+    pub fn with_applier<A, F>(mut self, name: impl Into<String>, make: F) -> Self
+    where
+        A: Applier<L, N> + 'static,
+        F: Fn(&[&str]) -> Result<A, String> + 'static,
+    {
+        let make = move |args: &[&str]| make(args).map(|a| DynApplier(Arc::new(a)));
+        self.appliers.insert(name.into(), Box::new(make));
+        self
+    }
+
+    /// Parses the rules in `input`.
+    ///
+    /// Errors are reported with the line the offending rule starts on.
+    pub fn parse(&self, input: &str) -> Result<Vec<Rewrite<L, N>>, String> {
+        let mut rules: Vec<Rewrite<L, N>> = vec![];
+        for (line, rule) in split_rules(input)? {
+            let new_rules = self
+                .parse_rule(&rule)
+                .map_err(|err| format!("line {}: {}", line, err))?;
+            for rw in new_rules {
+                if rules.iter().any(|r| r.name() == rw.name()) {
+                    return Err(format!("line {}: duplicate rule name {}", line, rw.name()));
+                }
+                rules.push(rw);
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Reads the file at `path` and parses the rules in it.
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<Vec<Rewrite<L, N>>, String> {
+        let path = path.as_ref();
+        let input = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        self.parse(&input)
+            .map_err(|err| format!("{}:{}", path.display(), err))
+    }
+
+    fn parse_rule(&self, rule: &str) -> Result<Vec<Rewrite<L, N>>, String> {
+        let colon = rule
+            .find(':')
+            .ok_or_else(|| format!("Expected 'name: lhs => rhs', found '{}'", rule))?;
+        let name = rule[..colon].trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Bad rule name '{}'", name));
+        }
+
+        let words = split_words(&rule[colon + 1..]);
+        let mut sections = words.split(|w| *w == "if");
+        let rewrite = sections.next().unwrap();
+        let conditions = sections
+            .map(|cond| match cond.split_first() {
+                Some((cond_name, args)) => self.make_condition(cond_name, args),
+                None => Err(format!("Missing condition after 'if' in rule {}", name)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let arrow = rewrite
+            .iter()
+            .position(|w| *w == "=>" || *w == "<=>")
+            .ok_or_else(|| format!("Missing '=>' or '<=>' in rule {}", name))?;
+        let (lhs, rhs) = (&rewrite[..arrow], &rewrite[arrow + 1..]);
+        let lhs: Pattern<L> = match lhs {
+            [lhs] => parse_pattern(lhs)?,
+            _ => {
+                return Err(format!(
+                    "Expected one pattern left of the arrow in rule {}",
+                    name
+                ))
+            }
+        };
+
+        if rewrite[arrow] == "=>" {
+            let applier = match rhs {
+                [first, args @ ..] if first.starts_with('@') => {
+                    self.make_applier(&first[1..], args)?
+                }
+                [rhs] => DynApplier(Arc::new(parse_pattern::<L>(rhs)?)),
+                _ => {
+                    return Err(format!(
+                        "Expected one pattern or '@applier' right of the arrow in rule {}",
+                        name
+                    ))
+                }
+            };
+            let rw = Rewrite::new(name, lhs, add_conditions(applier, &conditions))?;
+            Ok(vec![rw])
+        } else {
+            let rhs: Pattern<L> = match rhs {
+                [rhs] => parse_pattern(rhs)?,
+                _ => {
+                    return Err(format!(
+                        "Expected one pattern right of the arrow in bidirectional rule {}",
+                        name
+                    ))
+                }
+            };
+            let forward = add_conditions(DynApplier(Arc::new(rhs.clone())), &conditions);
+            let backward = add_conditions(DynApplier(Arc::new(lhs.clone())), &conditions);
+            Ok(vec![
+                Rewrite::new(name, lhs, forward)?,
+                Rewrite::new(format!("{}-rev", name), rhs, backward)?,
+            ])
+        }
+    }
+
+    fn make_condition(&self, name: &str, args: &[&str]) -> Result<DynCondition<L, N>, String> {
+        let make = self
+            .conditions
+            .get(name)
+            .ok_or_else(|| format!("Unknown condition {}", name))?;
+        make(args).map_err(|err| format!("Bad condition {}: {}", name, err))
+    }
+
+    fn make_applier(&self, name: &str, args: &[&str]) -> Result<DynApplier<L, N>, String> {
+        let make = self
+            .appliers
+            .get(name)
+            .ok_or_else(|| format!("Unknown applier @{}", name))?;
+        make(args).map_err(|err| format!("Bad applier @{}: {}", name, err))
+    }
+}
+
+fn parse_pattern<L: Language>(s: &str) -> Result<Pattern<L>, String> {
+    s.parse()
+        .map_err(|err| format!("Failed to parse pattern {}: {}", s, err))
+}
+
+// the first condition is checked first, like in the rewrite! macro
+fn add_conditions<L, N>(
+    mut applier: DynApplier<L, N>,
+    conditions: &[DynCondition<L, N>],
+) -> DynApplier<L, N>
+where
+    L: Language + 'static,
+    N: Analysis<L> + 'static,
+{
+    for condition in conditions.iter().rev() {
+        applier = DynApplier(Arc::new(ConditionalApplier {
+            condition: condition.clone(),
+            applier,
+        }));
+    }
+    applier
+}
+
+// joins lines into rules (with the line each one starts on),
+// dropping comments and blank lines
+fn split_rules(input: &str) -> Result<Vec<(usize, String)>, String> {
+    let mut rules = vec![];
+    let mut current: Option<(usize, String)> = None;
+    let mut depth = 0i32;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // a line starting with a name always starts a new rule, even if
+        // the one before is incomplete, so that one gets reported on its
+        // own; a complete rule ends unless this line continues it
+        let first = line.split_whitespace().next();
+        let continues = matches!(first, Some("=>") | Some("<=>") | Some("if"));
+        let complete = current.as_ref().map_or(false, |(_, r)| is_complete(r));
+        if depth == 0 && (starts_rule(line) || (!continues && complete)) {
+            rules.extend(current.take());
+        }
+
+        for c in line.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            }
+        }
+        let (_, rule) = current.get_or_insert_with(|| (i + 1, String::new()));
+        rule.push(' ');
+        rule.push_str(line);
+        if depth < 0 {
+            return Err(format!("line {}: unbalanced ')'", i + 1));
+        }
+    }
+    if let Some((line, rule)) = current {
+        if depth > 0 {
+            return Err(format!("line {}: unbalanced '('", line));
+        }
+        rules.push((line, rule));
+    }
+    Ok(rules)
+}
+
+// whether a rule has an arrow with something on both sides, and no
+// dangling `if`
+fn is_complete(rule: &str) -> bool {
+    let words = split_words(rule);
+    let arrow = words.iter().position(|w| *w == "=>" || *w == "<=>");
+    arrow.map_or(false, |i| i + 1 < words.len()) && words.last() != Some(&"if")
+}
+
+// whether a line starts with `name:`
+fn starts_rule(line: &str) -> bool {
+    match line.find(':') {
+        Some(colon) => {
+            let name = &line[..colon];
+            !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        }
+        None => false,
+    }
+}
+
+// splits on whitespace outside of parentheses
+fn split_words(s: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    words.push(&s[start..i]);
+                }
+                continue;
+            }
+            _ => (),
+        }
+        start.get_or_insert(i);
+    }
+    words.extend(start.map(|start| &s[start..]));
+    words
+}
+
+struct DynCondition<L, N>(Arc<dyn Condition<L, N>>);
+
+impl<L, N> Clone for DynCondition<L, N> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<L: Language, N: Analysis<L>> Condition<L, N> for DynCondition<L, N> {
+    fn check(&self, egraph: &mut EGraph<L, N>, eclass: Id, subst: &Subst) -> bool {
+        self.0.check(egraph, eclass, subst)
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        self.0.vars()
+    }
+}
+
+struct DynApplier<L, N>(Arc<dyn Applier<L, N>>);
+
+impl<L: Language, N: Analysis<L>> Applier<L, N> for DynApplier<L, N> {
+    fn apply_one(&self, egraph: &mut EGraph<L, N>, eclass: Id, subst: &Subst) -> Vec<Id> {
+        self.0.apply_one(egraph, eclass, subst)
+    }
+
+    fn apply_matches(&self, egraph: &mut EGraph<L, N>, matches: &[SearchMatches]) -> Vec<Id> {
+        self.0.apply_matches(egraph, matches)
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        self.0.vars()
+    }
+
+    fn get_pattern_ast(&self) -> Option<&PatternAst<L>> {
+        self.0.get_pattern_ast()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    fn parser() -> RuleParser<S, ()> {
+        RuleParser::new()
+            .with_condition("is_not_zero", |args: &[&str]| {
+                let var: Var = args.first().ok_or("missing variable")?.parse()?;
+                let zero = S::leaf("0");
+                Ok(move |egraph: &mut EGraph<S, ()>, _: Id, subst: &Subst| {
+                    !egraph[subst[var]].nodes.contains(&zero)
+                })
+            })
+            .with_applier("double", |args: &[&str]| {
+                let pattern: Result<Pattern<S>, _> = format!("(+ {} {})", args[0], args[0]).parse();
+                pattern.map_err(|err| err.to_string())
+            })
+    }
+
+    #[test]
+    fn parse_rules() {
+        crate::init_logger();
+        let rules = parser()
+            .parse(
+                "
+                # commutativity
+                comm-add: (+ ?a ?b) => (+ ?b ?a)
+                assoc-add: (+ ?a (+ ?b ?c))
+                       <=> (+ (+ ?a ?b) ?c)
+
+                cancel-div: (/ ?a ?a) => 1 if is_not_zero ?a
+                mul-two: (* 2 ?a) => @double ?a
+                ",
+            )
+            .unwrap();
+        let names: Vec<&str> = rules.iter().map(|r| r.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "comm-add",
+                "assoc-add",
+                "assoc-add-rev",
+                "cancel-div",
+                "mul-two"
+            ]
+        );
+
+        let start = "(+ (/ x x) (+ (/ 0 0) (* 2 y)))".parse().unwrap();
+        let runner = Runner::default()
+            .with_iter_limit(5)
+            .with_expr(&start)
+            .run(&rules);
+        let egraph = &runner.egraph;
+        let expected: Pattern<S> = "(+ 1 (+ (/ 0 0) (+ y y)))".parse().unwrap();
+        assert!(expected.search_eclass(egraph, runner.roots[0]).is_some());
+
+        // the condition kept 0/0 from being cancelled
+        let one = egraph.lookup(S::leaf("1")).unwrap();
+        let div_zero: Pattern<S> = "(/ 0 0)".parse().unwrap();
+        let matches = div_zero.search(egraph);
+        assert_eq!(matches.len(), 1);
+        assert_ne!(egraph.find(matches[0].eclass), one);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parser = parser();
+        let err = |input: &str| parser.parse(input).unwrap_err();
+
+        assert!(err("comm: (+ ?a ?b) (+ ?b ?a)").contains("Missing '=>'"));
+        assert!(err("div: (/ ?a ?a) => 1 if is_one ?a").contains("Unknown condition"));
+        assert!(err("div: (/ ?a ?a) => 1 if is_not_zero").contains("missing variable"));
+        assert!(err("bad: (+ ?a ?b) => ?c").contains("unbound var"));
+        assert!(err("rev: (* 2 ?a) <=> @double ?a").contains("bidirectional"));
+        assert!(err("a: x => y\na: y => x").starts_with("line 2"));
+        assert!(err("\n\nopen: (+ ?a\n   ?b => ?a").starts_with("line 3"));
+        assert_eq!(
+            err("a: x => y\nb: (+ ?a ?b) ?c\nc: y => x"),
+            "line 2: Missing '=>' or '<=>' in rule b"
+        );
+        assert_eq!(
+            err("a: x => y if\nb: y => x"),
+            "line 1: Missing condition after 'if' in rule a"
+        );
+    }
+
+    #[test]
+    fn parse_multiline() {
+        let rules = parser()
+            .parse(
+                "
+                comm-add:
+                    (+ ?a ?b) => (+ ?b ?a)
+                cancel-div: (/ ?a ?a) =>
+                    1
+                    if is_not_zero ?a
+                mul-two: (* 2 ?a)
+                    => @double ?a
+                ",
+            )
+            .unwrap();
+        let names: Vec<&str> = rules.iter().map(|r| r.name()).collect();
+        assert_eq!(names, vec!["comm-add", "cancel-div", "mul-two"]);
+    }
+}
@@ -1,3 +1,9 @@
+use std::any::Any;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use log::*;
 
 use crate::*;
@@ -46,6 +52,24 @@ Here are some of the things [`Runner`] does for you:
   You can set a upper limit on the number of enodes in the egraph.
   If this limit is hit, it stops with
   [`StopReason::NodeLimit`].
+  You can also limit the number of eclasses, in which case it stops
+  with [`StopReason::ClassLimit`].
+
+- Application limits
+
+  You can limit how many times rules are applied over the whole run,
+  in total or for a particular rule.
+  If one of these limits is hit, it stops with
+  [`StopReason::ApplicationLimit`] or
+  [`StopReason::RuleApplicationLimit`].
+
+- Memory limit
+
+  You can set a upper limit on the
+  [estimated memory usage](EGraph::estimated_memory()) of the egraph.
+  It is checked at the start of each iteration and after applying the
+  rules, and if it is hit, the runner stops with
+  [`StopReason::MemoryLimit`].
 
 - Time limit
 
@@ -53,6 +77,22 @@ Here are some of the things [`Runner`] does for you:
   If this limit is hit, it stops with
   [`StopReason::TimeLimit`].
 
+- Goals
+
+  You can give the [`Runner`] goals, like two terms becoming equal or
+  an eclass matching a pattern, with
+  [`with_goal`](Runner::with_goal()),
+  [`with_goal_pattern`](Runner::with_goal_pattern()) and
+  [`with_goal_patterns`](Runner::with_goal_patterns()).
+  They are checked after every rebuild, and once one is reached, it
+  stops with [`StopReason::GoalReached`].
+
+- Cancellation
+
+  A [`StopHandle`] from [`stop_handle`](Runner::stop_handle()) lets
+  another thread stop the [`Runner`], which then stops with
+  [`StopReason::Cancelled`].
+
 - Rule scheduling
 
   Some rules enable themselves, blowing up the [`EGraph`] and
@@ -62,6 +102,27 @@ Here are some of the things [`Runner`] does for you:
 
   [`BackoffScheduler`] is the default scheduler.
 
+- Incremental search
+
+  With [`with_incremental_search`](Runner::with_incremental_search()),
+  each rule only searches the part of the [`EGraph`] that changed since
+  it last ran (see [`Searcher::search_since`]), instead of finding
+  the same matches again every iteration.
+
+- Parallel search
+
+  With the `parallel` feature,
+  [`with_parallel_search`](Runner::with_parallel_search()) makes
+  [`Pattern`]s and [`MultiPattern`]s search their candidate eclasses
+  concurrently on the current [rayon](https://docs.rs/rayon) thread
+  pool. The matches are collected in the same order as a sequential
+  search, so the apply phase, and therefore the whole run, is unaffected.
+  Only the eclasses within one rule's search run in parallel; the
+  rules themselves are still searched one after another, since the
+  [`RewriteScheduler`] sees each search in turn.
+  To limit the number of threads, run the [`Runner`] inside your own
+  `rayon::ThreadPool`.
+
 [`Runner`] generates [`Iteration`]s that record some data about
 each iteration.
 You can add your own data to this by implementing the
@@ -152,10 +213,31 @@ pub struct Runner<L: Language, N: Analysis<L>, IterData = ()> {
     // limits
     iter_limit: usize,
     node_limit: usize,
+    class_limit: Option<usize>,
+    memory_limit: Option<usize>,
     time_limit: Duration,
+    application_limit: Option<usize>,
+    rule_application_limits: IndexMap<String, usize>,
+
+    // the number of times each rule was applied over the whole run
+    applications: IndexMap<String, usize>,
 
     start_time: Option<Instant>,
-    scheduler: Box<dyn RewriteScheduler<L, N>>,
+    scheduler: Box<dyn AnyScheduler<L, N>>,
+
+    incremental_search: bool,
+    // the egraph timestamp at which each rule was last fully searched
+    search_timestamps: IndexMap<String, usize>,
+
+    stop_handle: StopHandle,
+    goals: Vec<Goal<L>>,
+    observers: Vec<Box<dyn RunnerObserver<L, N, IterData>>>,
+}
+
+enum Goal<L> {
+    Equal(Id, Id),
+    // reached once the eclass matches every pattern
+    Patterns(Id, Vec<Pattern<L>>),
 }
 
 impl<L, N> Default for Runner<L, N, ()>
@@ -171,7 +253,7 @@ where
 /// Error returned by [`Runner`] when it stops.
 ///
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde-1", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub enum StopReason {
     /// The egraph saturated, i.e., there was an iteration where we
     /// didn't learn anything new from applying the rules.
@@ -180,21 +262,94 @@ pub enum StopReason {
     IterationLimit(usize),
     /// The enode limit was hit. The data is the enode limit.
     NodeLimit(usize),
+    /// The eclass limit was hit. The data is the number of eclasses.
+    ClassLimit(usize),
+    /// The memory limit was hit. The data is the estimated memory
+    /// usage in bytes.
+    MemoryLimit(usize),
     /// The time limit was hit. The data is the time limit in seconds.
     TimeLimit(f64),
+    /// The limit on the total number of rule applications was hit.
+    /// The data is the number of applications.
+    ApplicationLimit(usize),
+    /// The application limit of a rule was hit.
+    /// The data is the name of the rule.
+    RuleApplicationLimit(String),
+    /// The runner was stopped through its [`StopHandle`].
+    Cancelled,
+    /// A goal was reached. The data is the index of the goal, in the
+    /// order the goals were added.
+    GoalReached(usize),
     /// Some other reason to stop.
     Other(String),
 }
 
+/// A thread-safe handle to stop a running [`Runner`].
+///
+/// Get one with [`Runner::stop_handle`] before calling
+/// [`run`](Runner::run()), and call [`stop`](StopHandle::stop()) from
+/// anywhere, for example another thread enforcing a deadline.
+/// The [`Runner`] checks the handle along with its other limits, after
+/// searching or applying each rule, and searches check it between
+/// eclasses, so it stops soon after, with [`StopReason::Cancelled`].
+/// The matches of a search cut short this way are not applied.
+/// Searching a single eclass or applying a rule is not interrupted.
+/// Whenever [`run`](Runner::run()) returns, whatever the
+/// [`StopReason`], the handle is reset, so the [`Runner`] can be run
+/// again and stopped again.
+/// A [`stop`](StopHandle::stop()) requested between runs cancels the
+/// next run at its first iteration.
+///
+/// # Example
+/// ```
+/// use egg::{*, SymbolLang as S};
+/// let rules: &[Rewrite<S, ()>] = &[
+///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+/// ];
+///
+/// let runner = Runner::<S, ()>::default().with_expr(&"(+ a b)".parse().unwrap());
+/// let handle = runner.stop_handle();
+/// assert!(!handle.is_stopped());
+///
+/// // this could be another thread
+/// handle.stop();
+///
+/// let runner = runner.run(rules);
+/// assert!(matches!(runner.stop_reason, Some(StopReason::Cancelled)));
+/// assert!(!handle.is_stopped());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// Asks the [`Runner`] to stop.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed)
+    }
+
+    /// Returns `true` if [`stop`](StopHandle::stop()) has been called.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    fn reset(&self) {
+        self.stopped.store(false, Ordering::Relaxed)
+    }
+}
+
 /// Data generated by running a [`Runner`] one iteration.
 ///
 /// If the `serde-1` feature is enabled, this implements
-/// [`serde::Serialize`][ser], which is useful if you want to output
-/// this as a JSON or some other format.
+/// [`serde::Serialize`][ser] and [`serde::Deserialize`][de], which is
+/// useful if you want to output this as a JSON or some other format
+/// and load it back later.
 ///
 /// [ser]: https://docs.rs/serde/latest/serde/trait.Serialize.html
+/// [de]: https://docs.rs/serde/latest/serde/trait.Deserialize.html
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde-1", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct Iteration<IterData> {
     /// The number of enodes in the egraph at the start of this
@@ -203,9 +358,18 @@ pub struct Iteration<IterData> {
     /// The number of eclasses in the egraph at the start of this
     /// iteration.
     pub egraph_classes: usize,
+    /// The [estimated memory usage](EGraph::estimated_memory()) of the
+    /// egraph in bytes at the start of this iteration.
+    pub egraph_memory: usize,
     /// A map from rule name to number of times it was _newly_ applied
     /// in this iteration.
     pub applied: IndexMap<String, usize>,
+    /// A map from rule name to more detailed statistics about that
+    /// rule in this iteration, for every rule that was searched.
+    pub rules: IndexMap<String, RuleIterationStats>,
+    /// The rules the [`RewriteScheduler`] banned in this iteration,
+    /// see [`RewriteScheduler::take_bans`].
+    pub bans: Vec<BanEvent>,
     /// Seconds spent running hooks.
     pub hook_time: f64,
     /// Seconds spent searching in this iteration.
@@ -225,6 +389,164 @@ pub struct Iteration<IterData> {
     pub stop_reason: Option<StopReason>,
 }
 
+/// Statistics about one rule in one [`Iteration`].
+///
+/// See [`Iteration::rules`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct RuleIterationStats {
+    /// Seconds spent searching for this rule.
+    pub search_time: f64,
+    /// Seconds spent applying this rule.
+    pub apply_time: f64,
+    /// The number of matches the search returned.
+    pub matches: usize,
+    /// The number of times the rule was _newly_ applied, as in
+    /// [`Iteration::applied`].
+    pub applied: usize,
+    /// Whether the [`RewriteScheduler`] had banned this rule in this
+    /// iteration (see [`RewriteScheduler::is_banned`]).
+    pub banned: bool,
+    /// The number of matches the [`RewriteScheduler`] dropped to stay
+    /// under a match limit instead of banning the rule (see
+    /// [`RewriteScheduler::dropped_matches`]). They are not counted in
+    /// [`matches`](RuleIterationStats::matches).
+    pub dropped: usize,
+    /// How the matches that were kept were picked, if some were
+    /// dropped: [`MatchLimitPolicy::Truncate`] or
+    /// [`MatchLimitPolicy::Sample`].
+    pub limit_policy: Option<MatchLimitPolicy>,
+}
+
+/// A rule being banned by a [`RewriteScheduler`], recorded in
+/// [`Iteration::bans`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+pub struct BanEvent {
+    /// The name of the banned rule.
+    pub rule: String,
+    /// The number of matches the rule found.
+    pub matches: usize,
+    /// The match limit that was exceeded.
+    pub match_limit: usize,
+    /// The number of iterations the rule is banned for.
+    pub ban_length: usize,
+}
+
+/// A summary of a [`Runner`]'s run, from [`Runner::report`].
+///
+/// This implements [`Display`](std::fmt::Display) in the format of
+/// [`Runner::print_report`].
+/// If the `serde-1` feature is enabled, this implements
+/// [`serde::Serialize`][ser], and with the `reports` feature
+/// [`to_json`](RunReport::to_json()) turns it into JSON, which is
+/// handy to collect the results of many runs.
+///
+/// # Example
+/// ```
+/// use egg::{*, SymbolLang as S};
+/// let rules: &[Rewrite<S, ()>] = &[rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+/// let runner = Runner::<S, ()>::default()
+///     .with_expr(&"(+ a b)".parse().unwrap())
+///     .run(rules);
+///
+/// let report = runner.report();
+/// assert_eq!(report.iterations.len(), runner.iterations.len());
+/// assert_eq!(report.rules["commute-add"].applied, 1);
+/// assert!(report.to_string().contains("commute-add"));
+/// ```
+///
+/// [ser]: https://docs.rs/serde/latest/serde/trait.Serialize.html
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RunReport<'a, IterData> {
+    /// Why the [`Runner`] stopped, if it did.
+    pub stop_reason: Option<StopReason>,
+    /// The [`Iteration`]s of the run.
+    pub iterations: &'a [Iteration<IterData>],
+    /// The total number of enodes in the eclasses at the end.
+    pub egraph_nodes: usize,
+    /// The number of eclasses at the end.
+    pub egraph_classes: usize,
+    /// The size of the hashcons at the end.
+    pub memo_size: usize,
+    /// The [estimated memory usage](EGraph::estimated_memory()) at the end.
+    pub egraph_memory: usize,
+    /// The total number of rebuild iterations.
+    pub n_rebuilds: usize,
+    /// Total seconds spent running hooks.
+    pub hook_time: f64,
+    /// Total seconds spent searching.
+    pub search_time: f64,
+    /// Total seconds spent applying rules.
+    pub apply_time: f64,
+    /// Total seconds spent rebuilding.
+    pub rebuild_time: f64,
+    /// Total seconds spent in all iterations.
+    pub total_time: f64,
+    /// Statistics for each rule over the whole run, slowest rules first.
+    pub rules: IndexMap<String, RuleReport>,
+}
+
+/// Statistics about one rule over a whole run, see [`RunReport::rules`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct RuleReport {
+    /// Seconds spent searching for this rule.
+    pub search_time: f64,
+    /// Seconds spent applying this rule.
+    pub apply_time: f64,
+    /// The number of matches found.
+    pub matches: usize,
+    /// The number of times the rule was _newly_ applied.
+    pub applied: usize,
+    /// The number of iterations in which the rule was banned.
+    pub banned_iterations: usize,
+}
+
+impl<IterData> std::fmt::Display for RunReport<'_, IterData> {
+    #[rustfmt::skip]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let iters = self.iterations.len();
+        let rebuilds = self.n_rebuilds;
+        let total_time = self.total_time;
+
+        writeln!(f, "Runner report")?;
+        writeln!(f, "=============")?;
+        match &self.stop_reason {
+            Some(reason) => writeln!(f, "  Stop reason: {:?}", reason)?,
+            None => writeln!(f, "  Stop reason: not stopped")?,
+        }
+        writeln!(f, "  Iterations: {}", iters)?;
+        writeln!(f, "  Egraph size: {} nodes, {} classes, {} memo", self.egraph_nodes, self.egraph_classes, self.memo_size)?;
+        writeln!(f, "  Rebuilds: {}, {:.2} per iter", rebuilds, (rebuilds as f64) / (iters as f64))?;
+        writeln!(f, "  Total time: {}", total_time)?;
+        writeln!(f, "    Search:  ({:.2}) {}", self.search_time / total_time, self.search_time)?;
+        writeln!(f, "    Apply:   ({:.2}) {}", self.apply_time / total_time, self.apply_time)?;
+        writeln!(f, "    Rebuild: ({:.2}) {}", self.rebuild_time / total_time, self.rebuild_time)?;
+
+        writeln!(f, "  Rules (slowest first):")?;
+        for (name, rule) in &self.rules {
+            writeln!(f, "    {}: search {:.4}, apply {:.4}, {} matches, {} applied, banned in {} iters",
+                     name, rule.search_time, rule.apply_time, rule.matches, rule.applied, rule.banned_iterations)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "reports")]
+impl<IterData: serde::Serialize> RunReport<'_, IterData> {
+    /// Serializes this report to pretty-printed JSON.
+    ///
+    /// This requires the `reports` feature.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
 type RunnerResult<T> = std::result::Result<T, StopReason>;
 
 impl<L, N, IterData> Runner<L, N, IterData>
@@ -238,7 +560,13 @@ where
         Self {
             iter_limit: 30,
             node_limit: 10_000,
+            class_limit: None,
+            memory_limit: None,
             time_limit: Duration::from_secs(5),
+            application_limit: None,
+            rule_application_limits: Default::default(),
+
+            applications: Default::default(),
 
             egraph: EGraph::new(analysis),
             roots: vec![],
@@ -248,6 +576,13 @@ where
 
             start_time: None,
             scheduler: Box::new(BackoffScheduler::default()),
+
+            incremental_search: false,
+            search_timestamps: Default::default(),
+
+            stop_handle: StopHandle::default(),
+            goals: vec![],
+            observers: vec![],
         }
     }
 
@@ -261,11 +596,95 @@ where
         Self { node_limit, ..self }
     }
 
+    /// Sets the egraph size limit (in eclasses). Default: no limit
+    pub fn with_class_limit(self, class_limit: usize) -> Self {
+        Self {
+            class_limit: Some(class_limit),
+            ..self
+        }
+    }
+
+    /// Sets the limit on the
+    /// [estimated memory usage](EGraph::estimated_memory()) of the
+    /// egraph, in bytes. Default: no limit
+    ///
+    /// Since estimating the memory takes time linear in the number of
+    /// eclasses, this is only checked at the start of each iteration
+    /// and once all rules are applied, not after every rule like the
+    /// other limits.
+    pub fn with_memory_limit(self, memory_limit: usize) -> Self {
+        Self {
+            memory_limit: Some(memory_limit),
+            ..self
+        }
+    }
+
     /// Sets the runner time limit. Default: 5 seconds
     pub fn with_time_limit(self, time_limit: Duration) -> Self {
         Self { time_limit, ..self }
     }
 
+    /// Sets the limit on the total number of rule applications over all
+    /// runs of this runner. Default: no limit
+    ///
+    /// Like [`Iteration::applied`], this only counts applications that
+    /// changed the egraph.
+    pub fn with_application_limit(self, application_limit: usize) -> Self {
+        Self {
+            application_limit: Some(application_limit),
+            ..self
+        }
+    }
+
+    /// Sets the limit on the number of applications of the rule with the
+    /// given name over all runs of this runner. Default: no limit
+    ///
+    /// This is useful to bound a run whose growth is driven by one
+    /// rule. Like [`Iteration::applied`], this only counts applications
+    /// that changed the egraph.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let rules: &[Rewrite<S, ()>] = &[
+    ///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    /// ];
+    ///
+    /// let runner = Runner::<S, ()>::default()
+    ///     .with_expr(&"(+ a (+ b (+ c d)))".parse().unwrap())
+    ///     .with_rule_application_limit("assoc-add", 5)
+    ///     .run(rules);
+    /// match runner.stop_reason {
+    ///     Some(StopReason::RuleApplicationLimit(name)) => assert_eq!(name, "assoc-add"),
+    ///     reason => panic!("unexpected stop reason {:?}", reason),
+    /// }
+    /// ```
+    pub fn with_rule_application_limit(mut self, name: &str, limit: usize) -> Self {
+        self.rule_application_limits.insert(name.to_owned(), limit);
+        self
+    }
+
+    /// Only search for matches that may be new since each rule last ran.
+    /// Default: false
+    ///
+    /// Every rule still searches the whole [`EGraph`] the first time,
+    /// but afterwards it only looks at eclasses close to those that
+    /// changed since then (see [`Searcher::search_since`]).
+    /// If the [`RewriteScheduler`] skips or drops a rule's matches, that
+    /// rule searches from the same point again next time, so no match
+    /// is lost.
+    ///
+    /// Only the matched eclasses are considered, so [`Condition`]s or
+    /// [`Applier`]s that decide based on other parts of the
+    /// [`EGraph`] may not see a match again once those parts change.
+    pub fn with_incremental_search(self, incremental_search: bool) -> Self {
+        Self {
+            incremental_search,
+            ..self
+        }
+    }
+
     /// Add a hook to instrument or modify the behavior of a [`Runner`].
     /// Each hook will run at the beginning of each iteration, i.e. before
     /// all the rewrites.
@@ -297,6 +716,20 @@ where
         self
     }
 
+    /// Add a [`RunnerObserver`] to be notified of the progress of
+    /// this [`Runner`].
+    ///
+    /// Observers are called in insertion order.
+    /// Unlike [hooks](Runner::with_hook()), they cannot change the
+    /// egraph or stop the [`Runner`].
+    pub fn with_observer(
+        mut self,
+        observer: impl RunnerObserver<L, N, IterData> + 'static,
+    ) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
     /// Change out the [`RewriteScheduler`] used by this [`Runner`].
     /// The default one is [`BackoffScheduler`].
     ///
@@ -305,6 +738,13 @@ where
         Self { scheduler, ..self }
     }
 
+    /// Returns the [`RewriteScheduler`] used by this [`Runner`] if it
+    /// is an `S`, for example to look at a [`BackoffScheduler`]'s
+    /// [`rule_stats`](BackoffScheduler::rule_stats()) after a run.
+    pub fn scheduler_as<S: RewriteScheduler<L, N> + 'static>(&self) -> Option<&S> {
+        self.scheduler.as_any().downcast_ref()
+    }
+
     /// Add an expression to the egraph to be run.
     ///
     /// The eclass id of this addition will be recorded in the
@@ -316,15 +756,179 @@ where
         self
     }
 
+    /// Add a goal that two terms become equal.
+    ///
+    /// Both terms are added to the egraph, but not to the
+    /// [`roots`](Runner::roots).
+    /// The [`Runner`] checks its goals after every rebuild, and stops
+    /// with [`StopReason::GoalReached`] as soon as one is reached.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let rules: &[Rewrite<S, ()>] = &[
+    ///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    /// ];
+    ///
+    /// let runner = Runner::<S, ()>::default()
+    ///     .with_goal(
+    ///         &"(+ a (+ b c))".parse().unwrap(),
+    ///         &"(+ c (+ b a))".parse().unwrap(),
+    ///     )
+    ///     .run(rules);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::GoalReached(0))));
+    /// ```
+    pub fn with_goal(mut self, left: &RecExpr<L>, right: &RecExpr<L>) -> Self {
+        let left = self.egraph.add_expr(left);
+        let right = self.egraph.add_expr(right);
+        self.goals.push(Goal::Equal(left, right));
+        self
+    }
+
+    /// Add a goal that the given eclass matches a pattern.
+    ///
+    /// This is checked with [`Searcher::search_eclass`] after every
+    /// rebuild. See [`with_goal`](Runner::with_goal()).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let rules: &[Rewrite<S, ()>] = &[rewrite!("add-0"; "(+ ?a 0)" => "?a")];
+    ///
+    /// let runner = Runner::<S, ()>::default().with_expr(&"(f (+ x 0))".parse().unwrap());
+    /// let root = runner.roots[0];
+    /// let runner = runner
+    ///     .with_goal_pattern(root, "(f ?x)".parse().unwrap())
+    ///     .with_goal_pattern(root, "(f x)".parse().unwrap())
+    ///     .run(rules);
+    /// // the first goal holds from the start
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::GoalReached(0))));
+    /// assert_eq!(runner.iterations.len(), 1);
+    /// ```
+    pub fn with_goal_pattern(mut self, root: Id, pattern: Pattern<L>) -> Self {
+        self.goals.push(Goal::Patterns(root, vec![pattern]));
+        self
+    }
+
+    /// Add a goal that the given eclass matches all of the patterns.
+    /// The goal is reached once they all match at the same time.
+    ///
+    /// See [`with_goal_pattern`](Runner::with_goal_pattern()).
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let rules: &[Rewrite<S, ()>] = &[
+    ///     rewrite!("g-h"; "(g ?a)" => "(h ?a)"),
+    ///     rewrite!("h-x"; "(h ?a)" => "x"),
+    /// ];
+    ///
+    /// let runner = Runner::<S, ()>::default().with_expr(&"(f (g y))".parse().unwrap());
+    /// let root = runner.roots[0];
+    /// let runner = runner
+    ///     .with_goal_patterns(
+    ///         root,
+    ///         vec!["(f (h ?a))".parse().unwrap(), "(f x)".parse().unwrap()],
+    ///     )
+    ///     .run(rules);
+    /// // `(f (h ?a))` matches after one iteration, but `(f x)` takes two
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::GoalReached(0))));
+    /// assert_eq!(runner.iterations.len(), 2);
+    /// ```
+    pub fn with_goal_patterns(mut self, root: Id, patterns: Vec<Pattern<L>>) -> Self {
+        self.goals.push(Goal::Patterns(root, patterns));
+        self
+    }
+
+    /// Returns a [`StopHandle`] that can stop this [`Runner`] from
+    /// another thread.
+    ///
+    /// All handles from the same [`Runner`] share one flag.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop_handle.clone()
+    }
+
     /// Replace the [`EGraph`] of this `Runner`.
     pub fn with_egraph(self, egraph: EGraph<L, N>) -> Self {
-        Self { egraph, ..self }
+        Self {
+            egraph,
+            search_timestamps: Default::default(),
+            ..self
+        }
+    }
+
+    /// Enable explanations for this runner's [`EGraph`].
+    /// This must be called before adding any expressions.
+    ///
+    /// See [`EGraph::with_explanations_enabled`].
+    pub fn with_explanations_enabled(mut self) -> Self {
+        self.egraph = self.egraph.with_explanations_enabled();
+        self
+    }
+
+    /// Search this runner's [`EGraph`] in parallel.
+    ///
+    /// See [`EGraph::with_parallel_search`].
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_search(mut self) -> Self
+    where
+        L: Send + Sync,
+        N: Sync,
+        N::Data: Sync,
+    {
+        self.egraph = self.egraph.with_parallel_search();
+        self
     }
 
     /// Run this `Runner` until it stops.
     /// After this, the field
     /// [`stop_reason`](Runner::stop_reason) is guaranteed to be
     /// set.
+    ///
+    /// A `Runner` that has stopped can be run again, with the same or
+    /// different rules, to continue where it left off.
+    /// The previous [`stop_reason`](Runner::stop_reason) is cleared and
+    /// the time limit starts over, but the [`EGraph`],
+    /// [`iterations`](Runner::iterations), [`roots`](Runner::roots) and
+    /// the scheduler's state are kept.
+    /// Since the iteration limit counts all
+    /// [`iterations`](Runner::iterations), raise it with
+    /// [`with_iter_limit`](Runner::with_iter_limit()) to continue after
+    /// [`StopReason::IterationLimit`]; the same goes for the other limits,
+    /// including the application limits, which count the applications of
+    /// every run.
+    /// The [`StopHandle`] is reset whenever a run returns, so the next
+    /// run goes on until it is stopped again.
+    ///
+    /// # Example
+    /// ```
+    /// use egg::{*, SymbolLang as S};
+    /// let cheap: &[Rewrite<S, ()>] = &[rewrite!("add-0"; "(+ ?a 0)" => "?a")];
+    /// let expensive: &[Rewrite<S, ()>] = &[
+    ///     rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+    ///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    ///     rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    /// ];
+    ///
+    /// let runner = Runner::<S, ()>::default()
+    ///     .with_expr(&"(+ (+ a 0) (+ b (+ c 0)))".parse().unwrap())
+    ///     .run(cheap);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// let cheap_iters = runner.iterations.len();
+    ///
+    /// // run the expensive rules on the same egraph for a few iterations...
+    /// let runner = runner.with_iter_limit(cheap_iters + 2).run(expensive);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::IterationLimit(_))));
+    /// assert!(runner.iterations.len() > cheap_iters);
+    ///
+    /// // ...and then let them finish
+    /// let runner = runner.with_iter_limit(100).run(expensive);
+    /// assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+    /// assert_eq!(runner.roots.len(), 1);
+    /// let (_, best) = Extractor::new(&runner.egraph, AstSize).find_best(runner.roots[0]);
+    /// assert_eq!(best.as_ref().len(), 5);
+    /// ```
     pub fn run<'a, R>(mut self, rules: R) -> Self
     where
         R: IntoIterator<Item = &'a Rewrite<L, N>>,
@@ -333,44 +937,80 @@ where
     {
         let rules: Vec<&Rewrite<L, N>> = rules.into_iter().collect();
         check_rules(&rules);
+        self.stop_reason = None;
+        self.start_time = None;
         self.egraph.rebuild();
         loop {
             let iter = self.run_one(&rules);
             self.iterations.push(iter);
-            if let Some(stop_reason) = &self.iterations.last().unwrap().stop_reason {
+            let i = self.iterations.len() - 1;
+            let iter = &self.iterations[i];
+            for observer in &mut self.observers {
+                observer.iteration_end(i, iter, &self.egraph);
+            }
+            if let Some(stop_reason) = &iter.stop_reason {
                 info!("Stopping: {:?}", stop_reason);
+                for observer in &mut self.observers {
+                    observer.stopped(stop_reason, &self.egraph);
+                }
                 self.stop_reason = Some(stop_reason.clone());
                 break;
             }
         }
 
+        // a stop that came in as the run ended for another reason
+        // shouldn't cancel the next run
+        self.stop_handle.reset();
+
         assert!(!self.iterations.is_empty());
         assert!(self.stop_reason.is_some());
         self
     }
 
-    #[rustfmt::skip]
     /// Prints some information about a runners run.
+    ///
+    /// This prints the [`RunReport`] from [`report`](Runner::report()).
     pub fn print_report(&self) {
-        let search_time: f64 = self.iterations.iter().map(|i| i.search_time).sum();
-        let apply_time: f64 = self.iterations.iter().map(|i| i.apply_time).sum();
-        let rebuild_time: f64 = self.iterations.iter().map(|i| i.rebuild_time).sum();
-        let total_time: f64 = self.iterations.iter().map(|i| i.total_time).sum();
+        print!("{}", self.report())
+    }
 
-        let iters = self.iterations.len();
-        let rebuilds: usize = self.iterations.iter().map(|i| i.n_rebuilds).sum();
+    /// Returns a [`RunReport`] summarizing this runner's run so far.
+    pub fn report(&self) -> RunReport<'_, IterData> {
+        let iterations = &self.iterations;
+        let mut rules: IndexMap<String, RuleReport> = IndexMap::default();
+        for iteration in iterations {
+            for (name, stats) in &iteration.rules {
+                let total = rules.entry(name.clone()).or_default();
+                total.search_time += stats.search_time;
+                total.apply_time += stats.apply_time;
+                total.matches += stats.matches;
+                total.applied += stats.applied;
+                total.banned_iterations += stats.banned as usize;
+            }
+        }
+        rules.sort_by(|_, a, _, b| {
+            let a_time = a.search_time + a.apply_time;
+            let b_time = b.search_time + b.apply_time;
+            b_time
+                .partial_cmp(&a_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
 
-        let eg = &self.egraph;
-        println!("Runner report");
-        println!("=============");
-        println!("  Stop reason: {:?}", self.stop_reason.as_ref().unwrap());
-        println!("  Iterations: {}", iters);
-        println!("  Egraph size: {} nodes, {} classes, {} memo", eg.total_number_of_nodes(), eg.number_of_classes(), eg.total_size());
-        println!("  Rebuilds: {}, {:.2} per iter", rebuilds, (rebuilds as f64) / (iters as f64));
-        println!("  Total time: {}", total_time);
-        println!("    Search:  ({:.2}) {}", search_time / total_time, search_time);
-        println!("    Apply:   ({:.2}) {}", apply_time / total_time, apply_time);
-        println!("    Rebuild: ({:.2}) {}", rebuild_time / total_time, rebuild_time);
+        RunReport {
+            stop_reason: self.stop_reason.clone(),
+            egraph_nodes: self.egraph.total_number_of_nodes(),
+            egraph_classes: self.egraph.number_of_classes(),
+            memo_size: self.egraph.total_size(),
+            egraph_memory: self.egraph.estimated_memory(),
+            n_rebuilds: iterations.iter().map(|i| i.n_rebuilds).sum(),
+            hook_time: iterations.iter().map(|i| i.hook_time).sum(),
+            search_time: iterations.iter().map(|i| i.search_time).sum(),
+            apply_time: iterations.iter().map(|i| i.apply_time).sum(),
+            rebuild_time: iterations.iter().map(|i| i.rebuild_time).sum(),
+            total_time: iterations.iter().map(|i| i.total_time).sum(),
+            rules,
+            iterations,
+        }
     }
 
     fn run_one(&mut self, rules: &[&Rewrite<L, N>]) -> Iteration<IterData> {
@@ -379,10 +1019,19 @@ where
         info!("\nIteration {}", self.iterations.len());
 
         self.try_start();
-        let mut result = self.check_limits();
-
         let egraph_nodes = self.egraph.total_size();
         let egraph_classes = self.egraph.number_of_classes();
+        let egraph_memory = self.egraph.estimated_memory();
+
+        let mut result = self
+            .check_limits()
+            .and_then(|_| self.check_memory_limit(Some(egraph_memory)))
+            .and_then(|_| self.check_goals());
+
+        let i = self.iterations.len();
+        for observer in &mut self.observers {
+            observer.iteration_start(i, &self.egraph);
+        }
 
         let hook_time = Instant::now();
         let mut hooks = std::mem::take(&mut self.hooks);
@@ -397,20 +1046,48 @@ where
         let egraph_nodes_after_hooks = self.egraph.total_size();
         let egraph_classes_after_hooks = self.egraph.number_of_classes();
 
-        let i = self.iterations.len();
         trace!("EGraph {:?}", self.egraph.dump());
 
         let start_time = Instant::now();
+        let timestamp = self.egraph.timestamp();
 
+        // each rule's matches, and whether they are all the matches new
+        // since that rule's last timestamp
         let mut matches = Vec::new();
+        let mut rule_stats: IndexMap<String, RuleIterationStats> = IndexMap::default();
+        self.egraph.set_stop_handle(Some(self.stop_handle.clone()));
         result = result.and_then(|_| {
             rules.iter().try_for_each(|rule| {
-                let ms = self.scheduler.search_rewrite(i, &self.egraph, rule);
-                matches.push(ms);
+                let search_time = Instant::now();
+                let (ms, complete) = if self.incremental_search {
+                    let since = self.search_timestamps.get(rule.name()).map_or(0, |&t| t);
+                    self.scheduler
+                        .search_rewrite_since(i, &self.egraph, rule, since)
+                } else {
+                    let ms = self.scheduler.search_rewrite(i, &self.egraph, rule);
+                    (ms, false)
+                };
+
+                let stats = rule_stats.entry(rule.name().to_owned()).or_default();
+                stats.search_time += search_time.elapsed().as_secs_f64();
+                stats.matches += ms.iter().map(|m| m.substs.len()).sum::<usize>();
+                stats.banned |= self.scheduler.is_banned(i, rule);
+                if let Some((policy, dropped)) = self.scheduler.dropped_matches(i, rule) {
+                    stats.dropped += dropped;
+                    stats.limit_policy = Some(policy);
+                }
+                for observer in &mut self.observers {
+                    observer.rule_searched(i, rule.name(), stats);
+                }
+
+                matches.push((ms, complete));
                 self.check_limits()
             })
         });
 
+        self.egraph.set_stop_handle(None);
+        let bans = self.scheduler.take_bans();
+
         let search_time = start_time.elapsed().as_secs_f64();
         info!("Search time: {}", search_time);
 
@@ -418,23 +1095,42 @@ where
 
         let mut applied = IndexMap::default();
         result = result.and_then(|_| {
-            rules.iter().zip(matches).try_for_each(|(rw, ms)| {
-                let total_matches: usize = ms.iter().map(|m| m.substs.len()).sum();
-                debug!("Applying {} {} times", rw.name(), total_matches);
-
-                let actually_matched = self.scheduler.apply_rewrite(i, &mut self.egraph, rw, ms);
-                if actually_matched > 0 {
-                    if let Some(count) = applied.get_mut(rw.name()) {
-                        *count += actually_matched;
-                    } else {
-                        applied.insert(rw.name().to_owned(), actually_matched);
+            rules
+                .iter()
+                .zip(matches)
+                .try_for_each(|(rw, (ms, complete))| {
+                    let total_matches: usize = ms.iter().map(|m| m.substs.len()).sum();
+                    debug!("Applying {} {} times", rw.name(), total_matches);
+
+                    let apply_time = Instant::now();
+                    let actually_matched =
+                        self.scheduler.apply_rewrite(i, &mut self.egraph, rw, ms);
+                    let stats = &mut rule_stats[rw.name()];
+                    stats.apply_time += apply_time.elapsed().as_secs_f64();
+                    stats.applied += actually_matched;
+                    for observer in &mut self.observers {
+                        observer.rule_applied(i, rw.name(), stats);
                     }
-                    debug!("Applied {} {} times", rw.name(), actually_matched);
-                }
-                self.check_limits()
-            })
+                    if complete {
+                        self.search_timestamps
+                            .insert(rw.name().to_owned(), timestamp);
+                    }
+                    if actually_matched > 0 {
+                        if let Some(count) = applied.get_mut(rw.name()) {
+                            *count += actually_matched;
+                        } else {
+                            applied.insert(rw.name().to_owned(), actually_matched);
+                        }
+                        *self.applications.entry(rw.name().to_owned()).or_default() +=
+                            actually_matched;
+                        debug!("Applied {} {} times", rw.name(), actually_matched);
+                    }
+                    self.check_limits()
+                })
         });
 
+        result = result.and_then(|_| self.check_memory_limit(None));
+
         let apply_time = apply_time.elapsed().as_secs_f64();
         info!("Apply time: {}", apply_time);
 
@@ -443,12 +1139,17 @@ where
 
         let rebuild_time = rebuild_time.elapsed().as_secs_f64();
         info!("Rebuild time: {}", rebuild_time);
+        for observer in &mut self.observers {
+            observer.rebuilt(i, &self.egraph, n_rebuilds, rebuild_time);
+        }
         info!(
             "Size: n={}, e={}",
             self.egraph.total_size(),
             self.egraph.number_of_classes()
         );
 
+        result = result.and_then(|_| self.check_goals());
+
         let can_be_saturated = applied.is_empty()
             && self.scheduler.can_stop(i)
             && (egraph_nodes == egraph_nodes_after_hooks)
@@ -460,8 +1161,11 @@ where
 
         Iteration {
             applied,
+            rules: rule_stats,
+            bans,
             egraph_nodes,
             egraph_classes,
+            egraph_memory,
             hook_time,
             search_time,
             apply_time,
@@ -477,7 +1181,28 @@ where
         self.start_time.get_or_insert_with(Instant::now);
     }
 
+    fn check_goals(&self) -> RunnerResult<()> {
+        for (i, goal) in self.goals.iter().enumerate() {
+            let reached = match goal {
+                Goal::Equal(left, right) => self.egraph.find(*left) == self.egraph.find(*right),
+                Goal::Patterns(root, patterns) => patterns.iter().all(|pattern| {
+                    pattern
+                        .search_eclass(&self.egraph, self.egraph.find(*root))
+                        .is_some()
+                }),
+            };
+            if reached {
+                return Err(StopReason::GoalReached(i));
+            }
+        }
+        Ok(())
+    }
+
     fn check_limits(&self) -> RunnerResult<()> {
+        if self.stop_handle.is_stopped() {
+            return Err(StopReason::Cancelled);
+        }
+
         let elapsed = self.start_time.unwrap().elapsed();
         if elapsed > self.time_limit {
             return Err(StopReason::TimeLimit(elapsed.as_secs_f64()));
@@ -488,12 +1213,44 @@ where
             return Err(StopReason::NodeLimit(size));
         }
 
+        if let Some(class_limit) = self.class_limit {
+            let n_classes = self.egraph.number_of_classes();
+            if n_classes > class_limit {
+                return Err(StopReason::ClassLimit(n_classes));
+            }
+        }
+
+        if let Some(application_limit) = self.application_limit {
+            let total: usize = self.applications.values().sum();
+            if total > application_limit {
+                return Err(StopReason::ApplicationLimit(total));
+            }
+        }
+
+        for (name, &limit) in &self.rule_application_limits {
+            if self.applications.get(name).map_or(false, |&n| n > limit) {
+                return Err(StopReason::RuleApplicationLimit(name.clone()));
+            }
+        }
+
         if self.iterations.len() >= self.iter_limit {
             return Err(StopReason::IterationLimit(self.iterations.len()));
         }
 
         Ok(())
     }
+
+    // estimating the memory walks the whole egraph, so this isn't part
+    // of `check_limits`, which runs after every rule
+    fn check_memory_limit(&self, memory: Option<usize>) -> RunnerResult<()> {
+        if let Some(memory_limit) = self.memory_limit {
+            let memory = memory.unwrap_or_else(|| self.egraph.estimated_memory());
+            if memory > memory_limit {
+                return Err(StopReason::MemoryLimit(memory));
+            }
+        }
+        Ok(())
+    }
 }
 
 fn check_rules<L, N>(rules: &[&Rewrite<L, N>]) {
@@ -514,32 +1271,143 @@ fn check_rules<L, N>(rules: &[&Rewrite<L, N>]) {
     }
 }
 
-/** A way to customize how a [`Runner`] runs [`Rewrite`]s.
+/** A way to watch a [`Runner`] as it runs.
 
-This gives you a way to prevent certain [`Rewrite`]s from exploding
-the [`EGraph`] and dominating how much time is spent while running the
-[`Runner`].
+Add observers to a [`Runner`] with
+[`with_observer`](Runner::with_observer()).
+Every method has a default implementation that does nothing, so you
+only need to implement the events you care about.
+This is handy for progress bars, custom logging, or tracing spans.
+
+The `iteration` passed to each method is the index of the
+[`Iteration`] in progress, i.e. its position in
+[`Runner::iterations`].
+
+# Example
+```
+use egg::{*, SymbolLang as S};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Default)]
+struct MatchCounter(Rc<RefCell<usize>>);
+
+impl RunnerObserver<S, ()> for MatchCounter {
+    fn rule_searched(&mut self, _iteration: usize, _rule: &str, stats: &RuleIterationStats) {
+        *self.0.borrow_mut() += stats.matches;
+    }
+}
+
+let matches = Rc::new(RefCell::new(0));
+let rules: &[Rewrite<S, ()>] = &[rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+let runner = Runner::<S, ()>::default()
+    .with_expr(&"(+ a b)".parse().unwrap())
+    .with_observer(MatchCounter(matches.clone()))
+    .run(rules);
 
+let total: usize = runner.iterations.iter().map(|i| i.rules["commute-add"].matches).sum();
+assert_eq!(*matches.borrow(), total);
+```
 */
 #[allow(unused_variables)]
-pub trait RewriteScheduler<L, N>
+pub trait RunnerObserver<L, N, IterData = ()>
 where
     L: Language,
     N: Analysis<L>,
 {
-    /// Whether or not the [`Runner`] is allowed
-    /// to say it has saturated.
-    ///
-    /// This is only called when the runner is otherwise saturated.
-    /// Default implementation just returns `true`.
-    fn can_stop(&mut self, iteration: usize) -> bool {
-        true
-    }
+    /// Called at the start of an iteration, before the hooks run.
+    fn iteration_start(&mut self, iteration: usize, egraph: &EGraph<L, N>) {}
 
-    /// A hook allowing you to customize rewrite searching behavior.
-    /// Useful to implement rule management.
+    /// Called after searching for a rule.
     ///
-    /// Default implementation just calls
+    /// Only the search time, matches and ban of `stats` are filled in
+    /// at this point.
+    fn rule_searched(&mut self, iteration: usize, rule: &str, stats: &RuleIterationStats) {}
+
+    /// Called after applying a rule.
+    fn rule_applied(&mut self, iteration: usize, rule: &str, stats: &RuleIterationStats) {}
+
+    /// Called after the egraph is rebuilt at the end of the iteration,
+    /// with the number of rebuilds and the seconds spent rebuilding.
+    fn rebuilt(&mut self, iteration: usize, egraph: &EGraph<L, N>, n_rebuilds: usize, time: f64) {}
+
+    /// Called after an iteration, once it was added to
+    /// [`Runner::iterations`].
+    fn iteration_end(
+        &mut self,
+        iteration: usize,
+        data: &Iteration<IterData>,
+        egraph: &EGraph<L, N>,
+    ) {
+    }
+
+    /// Called when the [`Runner`] stops, after the last
+    /// [`iteration_end`](RunnerObserver::iteration_end()).
+    fn stopped(&mut self, reason: &StopReason, egraph: &EGraph<L, N>) {}
+}
+
+/** A way to customize how a [`Runner`] runs [`Rewrite`]s.
+
+This gives you a way to prevent certain [`Rewrite`]s from exploding
+the [`EGraph`] and dominating how much time is spent while running the
+[`Runner`].
+
+*/
+#[allow(unused_variables)]
+pub trait RewriteScheduler<L, N>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    /// Whether or not the [`Runner`] is allowed
+    /// to say it has saturated.
+    ///
+    /// This is only called when the runner is otherwise saturated.
+    /// Default implementation just returns `true`.
+    fn can_stop(&mut self, iteration: usize) -> bool {
+        true
+    }
+
+    /// Whether the given rewrite is banned in the given iteration,
+    /// i.e. its matches were skipped or dropped.
+    ///
+    /// This is only used for reporting, see
+    /// [`RuleIterationStats::banned`]. It is called right after
+    /// searching for the rewrite.
+    /// Default implementation just returns `false`.
+    fn is_banned(&self, iteration: usize, rewrite: &Rewrite<L, N>) -> bool {
+        false
+    }
+
+    /// If the last search for the given rewrite in the given iteration
+    /// returned only some of its matches to stay under a match limit,
+    /// returns how the kept matches were picked and how many were
+    /// dropped.
+    ///
+    /// This is only used for reporting, see
+    /// [`RuleIterationStats::dropped`]. It is called right after
+    /// searching for the rewrite.
+    /// Default implementation just returns `None`.
+    fn dropped_matches(
+        &self,
+        iteration: usize,
+        rewrite: &Rewrite<L, N>,
+    ) -> Option<(MatchLimitPolicy, usize)> {
+        None
+    }
+
+    /// Returns and forgets the rules banned since the last call.
+    ///
+    /// The [`Runner`] calls this after searching all the rules in an
+    /// iteration, and records the result in [`Iteration::bans`].
+    /// Default implementation just returns no bans.
+    fn take_bans(&mut self) -> Vec<BanEvent> {
+        vec![]
+    }
+
+    /// A hook allowing you to customize rewrite searching behavior.
+    /// Useful to implement rule management.
+    ///
+    /// Default implementation just calls
     /// [`Rewrite::search`](Rewrite::search()).
     fn search_rewrite(
         &mut self,
@@ -550,6 +1418,34 @@ where
         rewrite.search(egraph)
     }
 
+    /// Like [`search_rewrite`](RewriteScheduler::search_rewrite()), but
+    /// used when the [`Runner`] does
+    /// [incremental search](Runner::with_incremental_search()).
+    ///
+    /// The returned matches only need to include those that may be new
+    /// since `timestamp` (see [`Rewrite::search_since`]).
+    /// Along with the matches, return whether they are complete.
+    /// Return `false` if some of those matches were skipped or dropped,
+    /// for example because the rule is banned; the [`Runner`] will then
+    /// search from the same `timestamp` again next time.
+    /// If this returns `true`,
+    /// [`apply_rewrite`](RewriteScheduler::apply_rewrite()) should
+    /// apply all the matches.
+    ///
+    /// Default implementation just calls
+    /// [`search_rewrite`](RewriteScheduler::search_rewrite()), which
+    /// searches everything, so that schedulers overriding that method
+    /// keep working.
+    fn search_rewrite_since(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        timestamp: usize,
+    ) -> (Vec<SearchMatches>, bool) {
+        (self.search_rewrite(iteration, egraph, rewrite), true)
+    }
+
     /// A hook allowing you to customize rewrite application behavior.
     /// Useful to implement rule management.
     ///
@@ -567,12 +1463,29 @@ where
     }
 }
 
+// a `RewriteScheduler` that can be downcast to its own type, see
+// `Runner::scheduler_as`
+trait AnyScheduler<L: Language, N: Analysis<L>>: RewriteScheduler<L, N> {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<L, N, S> AnyScheduler<L, N> for S
+where
+    L: Language,
+    N: Analysis<L>,
+    S: RewriteScheduler<L, N> + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 /// A very simple [`RewriteScheduler`] that runs every rewrite every
 /// time.
 ///
 /// Using this is basically turning off rule scheduling.
 /// It uses the default implementation for all [`RewriteScheduler`]
-/// methods.
+/// methods, except that it searches incrementally when asked to.
 ///
 /// This is not the default scheduler; choose it with the
 /// [`with_scheduler`](Runner::with_scheduler())
@@ -585,6 +1498,15 @@ where
     L: Language,
     N: Analysis<L>,
 {
+    fn search_rewrite_since(
+        &mut self,
+        _iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        timestamp: usize,
+    ) -> (Vec<SearchMatches>, bool) {
+        (rewrite.search_since(egraph, timestamp), true)
+    }
 }
 
 /// A [`RewriteScheduler`] that implements exponentional rule backoff.
@@ -596,21 +1518,155 @@ where
 ///
 /// This seems effective at preventing explosive rules like
 /// associativity from taking an unfair amount of resources.
+/// Instead of being banned, a rule can also apply some of its matches
+/// and keep going, see [`MatchLimitPolicy`].
+///
+/// With [incremental search](Runner::with_incremental_search()), only
+/// the matches found by the incremental search count towards the limit.
 ///
 /// [`BackoffScheduler`] is configurable in the builder-pattern style.
 ///
 pub struct BackoffScheduler {
     default_match_limit: usize,
     default_ban_length: usize,
+    default_policy: MatchLimitPolicy,
     stats: IndexMap<String, RuleStats>,
+    bans: Vec<BanEvent>,
 }
 
-struct RuleStats {
+/// The statistics a [`BackoffScheduler`] keeps about a rule.
+///
+/// Get these from [`BackoffScheduler::rule_stats`], for example on the
+/// scheduler from [`Runner::scheduler_as`] after a run.
+///
+/// # Example
+/// ```
+/// use egg::{*, SymbolLang as S};
+/// let rules: &[Rewrite<S, ()>] = &[rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+/// let runner = Runner::<S, ()>::default()
+///     .with_scheduler(BackoffScheduler::default().with_initial_match_limit(0))
+///     .with_expr(&"(+ a b)".parse().unwrap())
+///     .with_iter_limit(3)
+///     .run(rules);
+///
+/// let scheduler = runner.scheduler_as::<BackoffScheduler>().unwrap();
+/// let stats = scheduler.rule_stats("commute-add").unwrap();
+/// assert!(stats.times_banned() > 0);
+/// assert_eq!(stats.match_limit(), 0);
+/// assert_eq!(runner.iterations[0].bans[0].rule, "commute-add");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RuleStats {
     times_applied: usize,
     banned_until: usize,
     times_banned: usize,
     match_limit: usize,
     ban_length: usize,
+    // `None` means the scheduler's default policy
+    policy: Option<MatchLimitPolicy>,
+    // seeded on the first sample
+    rng: Option<SplitMix64>,
+    // the iteration in which matches were last dropped, and how many
+    dropped: Option<(usize, usize)>,
+    // where the next slice of matches starts when truncating
+    offset: usize,
+}
+
+impl RuleStats {
+    /// The number of times the rule was searched without being banned.
+    pub fn times_applied(&self) -> usize {
+        self.times_applied
+    }
+
+    /// The number of times the rule was banned.
+    pub fn times_banned(&self) -> usize {
+        self.times_banned
+    }
+
+    /// The iteration until which the rule is banned.
+    /// The rule is banned in an iteration if it is less than this.
+    pub fn banned_until(&self) -> usize {
+        self.banned_until
+    }
+
+    /// The initial match limit of the rule.
+    /// The limit doubles every time the rule is banned.
+    pub fn match_limit(&self) -> usize {
+        self.match_limit
+    }
+
+    /// The initial ban length of the rule.
+    /// The length doubles every time the rule is banned.
+    pub fn ban_length(&self) -> usize {
+        self.ban_length
+    }
+
+    /// What happens when the rule exceeds its match limit, if it was
+    /// set for this rule with
+    /// [`rule_match_limit_policy`](BackoffScheduler::rule_match_limit_policy()).
+    /// Otherwise, the rule follows the scheduler's default, see
+    /// [`with_match_limit_policy`](BackoffScheduler::with_match_limit_policy()).
+    pub fn policy(&self) -> Option<MatchLimitPolicy> {
+        self.policy
+    }
+}
+
+/// What a [`BackoffScheduler`] does with the matches of a rule that
+/// exceeds its match limit.
+///
+/// Set it with
+/// [`with_match_limit_policy`](BackoffScheduler::with_match_limit_policy())
+/// or per rule with
+/// [`rule_match_limit_policy`](BackoffScheduler::rule_match_limit_policy()).
+/// The [`Runner`] cannot say it has saturated in an iteration where a
+/// rule dropped matches.
+///
+/// # Example
+/// ```
+/// use egg::{*, SymbolLang as S};
+/// let rules: &[Rewrite<S, ()>] = &[
+///     rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+///     rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+/// ];
+/// let scheduler = BackoffScheduler::default()
+///     .with_initial_match_limit(2)
+///     .rule_match_limit_policy("assoc-add", MatchLimitPolicy::Sample(42));
+/// let runner = Runner::<S, ()>::default()
+///     .with_scheduler(scheduler)
+///     .with_expr(&"(+ a (+ b (+ c (+ d e))))".parse().unwrap())
+///     .with_iter_limit(3)
+///     .run(rules);
+///
+/// // commute-add was banned, assoc-add never is
+/// let bans: Vec<&str> = runner.iterations.iter()
+///     .flat_map(|i| &i.bans)
+///     .map(|b| b.rule.as_str())
+///     .collect();
+/// assert!(bans.contains(&"commute-add"));
+/// assert!(!bans.contains(&"assoc-add"));
+/// for iteration in &runner.iterations {
+///     assert!(iteration.applied.get("assoc-add").map_or(true, |&n| n <= 2));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchLimitPolicy {
+    /// Drop all the matches and ban the rule for a while.
+    /// This is the default.
+    Ban,
+    /// Apply only a slice of the matches, up to the match limit.
+    /// Each time, the slice starts where the last one ended, so every
+    /// match gets its turn.
+    Truncate,
+    /// Apply a random sample of the matches, up to the match limit.
+    /// The sample is deterministic given the seed.
+    Sample(u64),
+}
+
+impl Default for MatchLimitPolicy {
+    fn default() -> Self {
+        MatchLimitPolicy::Ban
+    }
 }
 
 impl BackoffScheduler {
@@ -628,45 +1684,86 @@ impl BackoffScheduler {
         self
     }
 
-    fn rule_stats(&mut self, name: &str) -> &mut RuleStats {
+    /// Returns the statistics about the given rule, if it was
+    /// configured or searched.
+    pub fn rule_stats(&self, name: &str) -> Option<&RuleStats> {
+        self.stats.get(name)
+    }
+
+    /// Returns the statistics about every rule that was configured or
+    /// searched.
+    pub fn stats(&self) -> impl Iterator<Item = (&str, &RuleStats)> {
+        self.stats.iter().map(|(name, s)| (name.as_str(), s))
+    }
+
+    fn rule_stats_mut(&mut self, name: &str) -> &mut RuleStats {
         if self.stats.contains_key(name) {
             &mut self.stats[name]
         } else {
-            self.stats.entry(name.to_owned()).or_insert(RuleStats {
+            let stats = RuleStats {
                 times_applied: 0,
                 banned_until: 0,
                 times_banned: 0,
                 match_limit: self.default_match_limit,
                 ban_length: self.default_ban_length,
-            })
+                policy: None,
+                rng: None,
+                dropped: None,
+                offset: 0,
+            };
+            self.stats.entry(name.to_owned()).or_insert(stats)
         }
     }
 
     /// Never ban a particular rule.
     pub fn do_not_ban(mut self, name: &str) -> Self {
-        self.rule_stats(name).match_limit = usize::MAX;
+        self.rule_stats_mut(name).match_limit = usize::MAX;
         self
     }
 
     /// Set the initial match limit for a rule.
     pub fn rule_match_limit(mut self, name: &str, limit: usize) -> Self {
-        self.rule_stats(name).match_limit = limit;
+        self.rule_stats_mut(name).match_limit = limit;
         self
     }
 
     /// Set the initial ban length for a rule.
     pub fn rule_ban_length(mut self, name: &str, length: usize) -> Self {
-        self.rule_stats(name).ban_length = length;
+        self.rule_stats_mut(name).ban_length = length;
         self
     }
+
+    /// Set what happens to rules that exceed their match limit, unless
+    /// set per rule with
+    /// [`rule_match_limit_policy`](BackoffScheduler::rule_match_limit_policy()).
+    /// Default: [`MatchLimitPolicy::Ban`]
+    pub fn with_match_limit_policy(mut self, policy: MatchLimitPolicy) -> Self {
+        self.default_policy = policy;
+        self
+    }
+
+    /// Set what happens when a rule exceeds its match limit.
+    pub fn rule_match_limit_policy(mut self, name: &str, policy: MatchLimitPolicy) -> Self {
+        let stats = self.rule_stats_mut(name);
+        stats.policy = Some(policy);
+        stats.rng = None;
+        self
+    }
+
+    // the policy the rule follows
+    fn policy(&self, stats: &RuleStats) -> MatchLimitPolicy {
+        stats.policy.unwrap_or(self.default_policy)
+    }
 }
 
 impl Default for BackoffScheduler {
     fn default() -> Self {
         Self {
             stats: Default::default(),
+            bans: vec![],
             default_match_limit: 1_000,
             default_ban_length: 5,
+            default_policy: MatchLimitPolicy::Ban,
         }
     }
 }
@@ -677,6 +1774,12 @@ where
     N: Analysis<L>,
 {
     fn can_stop(&mut self, iteration: usize) -> bool {
+        // applying nothing proves nothing if some matches were dropped
+        let dropped = |s: &RuleStats| matches!(s.dropped, Some((i, _)) if i == iteration);
+        if self.stats.values().any(dropped) {
+            return false;
+        }
+
         let n_stats = self.stats.len();
 
         let mut banned: Vec<_> = self
@@ -718,13 +1821,62 @@ where
         }
     }
 
+    fn is_banned(&self, iteration: usize, rewrite: &Rewrite<L, N>) -> bool {
+        self.stats
+            .get(rewrite.name())
+            .map_or(false, |s| s.banned_until > iteration)
+    }
+
+    fn dropped_matches(
+        &self,
+        iteration: usize,
+        rewrite: &Rewrite<L, N>,
+    ) -> Option<(MatchLimitPolicy, usize)> {
+        let stats = self.stats.get(rewrite.name())?;
+        match stats.dropped {
+            Some((i, dropped)) if i == iteration => Some((self.policy(stats), dropped)),
+            _ => None,
+        }
+    }
+
+    fn take_bans(&mut self) -> Vec<BanEvent> {
+        std::mem::take(&mut self.bans)
+    }
+
     fn search_rewrite(
         &mut self,
         iteration: usize,
         egraph: &EGraph<L, N>,
         rewrite: &Rewrite<L, N>,
     ) -> Vec<SearchMatches> {
-        let stats = self.rule_stats(rewrite.name());
+        let (matches, _) = self.search_with_backoff(iteration, rewrite, || rewrite.search(egraph));
+        matches
+    }
+
+    fn search_rewrite_since(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        timestamp: usize,
+    ) -> (Vec<SearchMatches>, bool) {
+        self.search_with_backoff(iteration, rewrite, || {
+            rewrite.search_since(egraph, timestamp)
+        })
+    }
+}
+
+impl BackoffScheduler {
+    // returns the matches to apply, and whether those are all the matches
+    fn search_with_backoff<L, N>(
+        &mut self,
+        iteration: usize,
+        rewrite: &Rewrite<L, N>,
+        search: impl FnOnce() -> Vec<SearchMatches>,
+    ) -> (Vec<SearchMatches>, bool) {
+        let default_policy = self.default_policy;
+        let stats = self.rule_stats_mut(rewrite.name());
+        let policy = stats.policy.unwrap_or(default_policy);
 
         if iteration < stats.banned_until {
             debug!(
@@ -734,13 +1886,36 @@ where
                 stats.times_banned,
                 stats.banned_until,
             );
-            return vec![];
+            return (vec![], false);
         }
 
-        let matches = rewrite.search(egraph);
+        let matches = search();
         let total_len: usize = matches.iter().map(|m| m.substs.len()).sum();
         let threshold = stats.match_limit << stats.times_banned;
-        if total_len > threshold {
+        if total_len > threshold && policy != MatchLimitPolicy::Ban {
+            stats.times_applied += 1;
+            stats.dropped = Some((iteration, total_len - threshold));
+            let matches = match policy {
+                MatchLimitPolicy::Sample(seed) => {
+                    let rng = stats.rng.get_or_insert_with(|| SplitMix64::new(seed));
+                    let keep = rng.sample(total_len, threshold);
+                    filter_matches(matches, |i| keep.binary_search(&i).is_ok())
+                }
+                MatchLimitPolicy::Truncate | MatchLimitPolicy::Ban => {
+                    let start = stats.offset % total_len;
+                    stats.offset = start + threshold;
+                    take_matches(matches, start, threshold)
+                }
+            };
+            info!(
+                "Applying {} of {} matches of {} ({:?})",
+                threshold,
+                total_len,
+                rewrite.name(),
+                policy,
+            );
+            (matches, false)
+        } else if total_len > threshold {
             let ban_length = stats.ban_length << stats.times_banned;
             stats.times_banned += 1;
             stats.banned_until = iteration + ban_length;
@@ -753,14 +1928,632 @@ where
                 threshold,
                 total_len,
             );
-            vec![]
+            self.bans.push(BanEvent {
+                rule: rewrite.name().to_owned(),
+                matches: total_len,
+                match_limit: threshold,
+                ban_length,
+            });
+            (vec![], false)
         } else {
             stats.times_applied += 1;
+            (matches, true)
+        }
+    }
+}
+
+/** A [`RewriteScheduler`] that runs groups of rules, each with its own
+scheduler, following a plan.
+
+Each group is a named set of rules, added with
+[`with_group`](GroupScheduler::with_group()) together with the
+[`RewriteScheduler`] that handles those rules, like a
+[`SimpleScheduler`] or a [`BackoffScheduler`].
+Every rule given to the [`Runner`] must be in exactly one group.
+
+The plan is a sequence of steps that is repeated over and over:
+- [`then_saturate`](GroupScheduler::then_saturate()) runs only the
+  rules of a group until they apply no more,
+- [`then_run`](GroupScheduler::then_run()) runs only the rules of a
+  group for a number of iterations.
+
+A group that is not part of the current step is not searched at all.
+With no steps, every group runs in every iteration.
+The [`Runner`] can only say it has saturated once a whole round of
+the plan did not apply anything.
+
+# Example
+```
+use egg::{*, SymbolLang as S};
+let rules: &[Rewrite<S, ()>] = &[
+    rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+    rewrite!("mul-1"; "(* ?a 1)" => "?a"),
+    rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+];
+
+// normalize to saturation between every round of expansion
+let scheduler = GroupScheduler::default()
+    .with_group("cheap", &["add-0", "mul-1"], SimpleScheduler)
+    .with_group("expensive", &["commute-add", "assoc-add"], BackoffScheduler::default())
+    .then_saturate("cheap")
+    .then_run("expensive", 1);
+
+let runner = Runner::<S, ()>::default()
+    .with_scheduler(scheduler)
+    .with_expr(&"(+ (* a 1) (+ b (+ c 0)))".parse().unwrap())
+    .run(rules);
+
+assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+let (_, best) = Extractor::new(&runner.egraph, AstSize).find_best(runner.roots[0]);
+assert_eq!(best.as_ref().len(), 5);
+```
+*/
+pub struct GroupScheduler<L: Language, N: Analysis<L>> {
+    groups: IndexMap<String, Box<dyn AnyScheduler<L, N>>>,
+    // the group index of each rule
+    rule_groups: IndexMap<String, usize>,
+    steps: Vec<GroupStep>,
+
+    step: usize,
+    // the number of iterations and applications of the current step
+    step_iterations: usize,
+    step_applied: usize,
+    // the number of consecutive steps that applied nothing
+    idle_steps: usize,
+    iteration: Option<usize>,
+    iteration_applied: usize,
+    iteration_finished: bool,
+}
+
+enum GroupStep {
+    Saturate(usize),
+    Run(usize, usize),
+}
+
+impl<L, N> Default for GroupScheduler<L, N>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    fn default() -> Self {
+        Self {
+            groups: Default::default(),
+            rule_groups: Default::default(),
+            steps: vec![],
+            step: 0,
+            step_iterations: 0,
+            step_applied: 0,
+            idle_steps: 0,
+            iteration: None,
+            iteration_applied: 0,
+            iteration_finished: false,
+        }
+    }
+}
+
+impl<L, N> GroupScheduler<L, N>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    /// Add a group of rules, identified by their names, handled by the
+    /// given scheduler.
+    ///
+    /// Panics if there already is a group with this name, or if one of
+    /// the rules is already in a group.
+    pub fn with_group(
+        mut self,
+        name: &str,
+        rules: &[&str],
+        scheduler: impl RewriteScheduler<L, N> + 'static,
+    ) -> Self {
+        let index = self.groups.len();
+        let old = self.groups.insert(name.to_owned(), Box::new(scheduler));
+        assert!(old.is_none(), "Group '{}' was added twice", name);
+        for &rule in rules {
+            if let Some(&other) = self.rule_groups.get(rule) {
+                let (other, _) = self.groups.get_index(other).unwrap();
+                panic!("Rule '{}' is in groups '{}' and '{}'", rule, other, name);
+            }
+            self.rule_groups.insert(rule.to_owned(), index);
+        }
+        self
+    }
+
+    /// Returns the scheduler of the given group if it is an `S`, for
+    /// example to look at a [`BackoffScheduler`]'s statistics.
+    pub fn group_as<S: RewriteScheduler<L, N> + 'static>(&self, group: &str) -> Option<&S> {
+        self.groups.get(group)?.as_any().downcast_ref()
+    }
+
+    /// Add a step to the plan that runs the given group until it
+    /// applies no more.
+    ///
+    /// Panics if there is no such group.
+    pub fn then_saturate(mut self, group: &str) -> Self {
+        let index = self.group_index(group);
+        self.steps.push(GroupStep::Saturate(index));
+        self
+    }
+
+    /// Add a step to the plan that runs the given group for the given
+    /// number of iterations.
+    ///
+    /// Panics if there is no such group.
+    pub fn then_run(mut self, group: &str, iterations: usize) -> Self {
+        let index = self.group_index(group);
+        self.steps.push(GroupStep::Run(index, iterations));
+        self
+    }
+
+    fn group_index(&self, group: &str) -> usize {
+        match self.groups.get_index_of(group) {
+            Some(index) => index,
+            None => panic!("No group named '{}'", group),
+        }
+    }
+
+    fn rule_group(&self, rewrite: &Rewrite<L, N>) -> usize {
+        match self.rule_groups.get(rewrite.name()) {
+            Some(&index) => index,
+            None => panic!("Rule '{}' is not in any group", rewrite.name()),
+        }
+    }
+
+    fn is_active(&self, group: usize) -> bool {
+        match self.steps.get(self.step) {
+            None => true,
+            Some(GroupStep::Saturate(g)) | Some(GroupStep::Run(g, _)) => *g == group,
+        }
+    }
+
+    fn start_iteration(&mut self, iteration: usize) {
+        if self.iteration != Some(iteration) {
+            if let Some(previous) = self.iteration {
+                self.finish_iteration(previous);
+            }
+            self.iteration = Some(iteration);
+            self.iteration_applied = 0;
+            self.iteration_finished = false;
+        }
+    }
+
+    // moves on to the next step if the current one is done
+    fn finish_iteration(&mut self, iteration: usize) {
+        if self.iteration_finished {
+            return;
+        }
+        self.iteration_finished = true;
+        self.step_iterations += 1;
+        self.step_applied += self.iteration_applied;
+
+        let (group, done) = match self.steps.get(self.step) {
+            None => return,
+            Some(GroupStep::Run(g, n)) => (*g, self.step_iterations >= *n),
+            Some(GroupStep::Saturate(g)) => (
+                *g,
+                self.iteration_applied == 0 && self.groups[*g].can_stop(iteration),
+            ),
+        };
+
+        if done {
+            // a step that applied nothing but still has banned rules
+            // is not idle
+            let idle = self.step_applied == 0
+                && (matches!(self.steps[self.step], GroupStep::Saturate(_))
+                    || self.groups[group].can_stop(iteration));
+            if idle {
+                self.idle_steps += 1;
+            } else {
+                self.idle_steps = 0;
+            }
+            self.step = (self.step + 1) % self.steps.len();
+            self.step_iterations = 0;
+            self.step_applied = 0;
+            info!(
+                "Moving on to step {} of the group plan, {} idle steps",
+                self.step, self.idle_steps
+            );
+        }
+    }
+}
+
+impl<L, N> RewriteScheduler<L, N> for GroupScheduler<L, N>
+where
+    L: Language,
+    N: Analysis<L>,
+{
+    fn can_stop(&mut self, iteration: usize) -> bool {
+        if self.steps.is_empty() {
+            // every group's scheduler must be asked, so don't short-circuit
+            let can_stop: Vec<bool> = self
+                .groups
+                .values_mut()
+                .map(|s| s.can_stop(iteration))
+                .collect();
+            can_stop.into_iter().all(|can_stop| can_stop)
+        } else {
+            self.start_iteration(iteration);
+            self.finish_iteration(iteration);
+            self.idle_steps >= self.steps.len()
+        }
+    }
+
+    fn is_banned(&self, iteration: usize, rewrite: &Rewrite<L, N>) -> bool {
+        self.groups[self.rule_group(rewrite)].is_banned(iteration, rewrite)
+    }
+
+    fn dropped_matches(
+        &self,
+        iteration: usize,
+        rewrite: &Rewrite<L, N>,
+    ) -> Option<(MatchLimitPolicy, usize)> {
+        self.groups[self.rule_group(rewrite)].dropped_matches(iteration, rewrite)
+    }
+
+    fn take_bans(&mut self) -> Vec<BanEvent> {
+        let groups = self.groups.values_mut();
+        groups.flat_map(|s| s.take_bans()).collect()
+    }
+
+    fn search_rewrite(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+    ) -> Vec<SearchMatches> {
+        self.start_iteration(iteration);
+        let group = self.rule_group(rewrite);
+        if self.is_active(group) {
+            self.groups[group].search_rewrite(iteration, egraph, rewrite)
+        } else {
+            vec![]
+        }
+    }
+
+    fn search_rewrite_since(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        timestamp: usize,
+    ) -> (Vec<SearchMatches>, bool) {
+        self.start_iteration(iteration);
+        let group = self.rule_group(rewrite);
+        if self.is_active(group) {
+            self.groups[group].search_rewrite_since(iteration, egraph, rewrite, timestamp)
+        } else {
+            // skipped, so search from the same timestamp next time
+            (vec![], false)
+        }
+    }
+
+    fn apply_rewrite(
+        &mut self,
+        iteration: usize,
+        egraph: &mut EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        matches: Vec<SearchMatches>,
+    ) -> usize {
+        let group = self.rule_group(rewrite);
+        if !self.is_active(group) {
+            return 0;
+        }
+        let applied = self.groups[group].apply_rewrite(iteration, egraph, rewrite, matches);
+        self.iteration_applied += applied;
+        applied
+    }
+}
+
+/** A [`RewriteScheduler`] that spends a limited number of matches per
+iteration on the rules that seem to improve the [`Runner`]'s roots.
+
+At the start of each iteration, after the [`Runner`] has rebuilt the
+egraph, this extracts the roots with a [`CostFunction`] to check
+whether their cost went down since the last iteration.
+A rule's reward is a moving average of how often that happened after
+an iteration in which it applied.
+Rules that apply in the same iteration share the credit, and so do
+any hooks that changed the egraph.
+
+If the rules find more matches than the match budget in an
+iteration, the budget is split between the rules that found matches
+in proportion to their reward, plus an exploration weight so that
+no rule is starved.
+The shares add up to exactly the match budget.
+A rule that gets fewer matches than it found applies a different
+slice of them in each iteration.
+
+This trades saturation for faster convergence to cheap terms under
+tight limits: the [`Runner`] cannot say it has saturated in an
+iteration where matches were dropped, and rules are always searched
+from scratch, even with
+[incremental search](Runner::with_incremental_search()).
+
+# Example
+```
+use egg::{*, SymbolLang as S};
+let rules: &[Rewrite<S, ()>] = &[
+    rewrite!("commute-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+    rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    rewrite!("add-0"; "(+ ?a 0)" => "?a"),
+];
+
+let runner = Runner::<S, ()>::default()
+    .with_expr(&"(+ a (+ b (+ 0 (+ c 0))))".parse().unwrap());
+let scheduler = CostScheduler::new(AstSize, &runner.roots).with_match_budget(10);
+let runner = runner.with_scheduler(scheduler).with_iter_limit(10).run(rules);
+
+let (cost, _) = Extractor::new(&runner.egraph, AstSize).find_best(runner.roots[0]);
+assert_eq!(cost, 5);
+```
+*/
+pub struct CostScheduler<L: Language, CF: CostFunction<L>> {
+    cost_function: CF,
+    roots: Vec<Id>,
+    match_budget: usize,
+    exploration: f64,
+    decay: f64,
+
+    stats: IndexMap<String, CostRuleStats>,
+    costs: Option<Vec<CF::Cost>>,
+    iteration: Option<usize>,
+    // the number of matches each rule found in the current iteration
+    found: IndexMap<String, usize>,
+    // the rules that applied in the current iteration
+    applied: Vec<String>,
+    // each rule's share of the match budget in the current iteration,
+    // split once all the rules have been searched
+    budgets: Option<IndexMap<String, usize>>,
+    truncated: bool,
+}
+
+#[derive(Default)]
+struct CostRuleStats {
+    reward: f64,
+    // where the next slice of matches starts, if the rule is throttled
+    offset: usize,
+}
+
+impl<L, CF> CostScheduler<L, CF>
+where
+    L: Language,
+    CF: CostFunction<L> + Clone,
+{
+    /// Create a new [`CostScheduler`] that measures the cost of the
+    /// given roots, usually the [`Runner::roots`].
+    pub fn new(cost_function: CF, roots: &[Id]) -> Self {
+        Self {
+            cost_function,
+            roots: roots.to_vec(),
+            match_budget: 10_000,
+            exploration: 0.1,
+            decay: 0.5,
+            stats: Default::default(),
+            costs: None,
+            iteration: None,
+            found: Default::default(),
+            applied: Default::default(),
+            budgets: None,
+            truncated: false,
+        }
+    }
+
+    /// Set the total number of matches applied per iteration.
+    /// Default: 10,000
+    pub fn with_match_budget(mut self, match_budget: usize) -> Self {
+        self.match_budget = match_budget;
+        self
+    }
+
+    /// Set the weight every rule gets on top of its reward when
+    /// splitting the match budget.
+    /// Higher values split the budget more evenly.
+    /// Default: 0.1
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        assert!(exploration > 0.0, "Exploration weight must be positive");
+        self.exploration = exploration;
+        self
+    }
+
+    /// Set how much of a rule's reward is kept every time it applies,
+    /// between 0 (only the last iteration counts) and 1 (the reward
+    /// never changes).
+    /// Default: 0.5
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&decay),
+            "Decay must be between 0 and 1"
+        );
+        self.decay = decay;
+        self
+    }
+
+    /// The reward of the given rule, between 0 and 1.
+    pub fn reward(&self, name: &str) -> f64 {
+        self.stats.get(name).map_or(0.0, |s| s.reward)
+    }
+
+    fn start_iteration<N: Analysis<L>>(&mut self, iteration: usize, egraph: &EGraph<L, N>) {
+        if self.iteration == Some(iteration) {
+            return;
+        }
+        self.iteration = Some(iteration);
+        self.found.clear();
+        self.budgets = None;
+        self.truncated = false;
+
+        // the Runner has rebuilt the egraph since the last iteration, so
+        // the roots can be extracted here without rebuilding
+        let costs = self.root_costs(egraph);
+        let improved = match &self.costs {
+            Some(old) => costs.iter().zip(old).any(|(new, old)| new < old),
+            None => false,
+        };
+        self.costs = Some(costs);
+        for name in std::mem::take(&mut self.applied) {
+            self.update_reward(&name, improved);
+        }
+    }
+
+    fn root_costs<N: Analysis<L>>(&self, egraph: &EGraph<L, N>) -> Vec<CF::Cost> {
+        let mut extractor = Extractor::new(egraph, self.cost_function.clone());
+        self.roots
+            .iter()
+            .map(|&root| extractor.find_best_cost(root))
+            .collect()
+    }
+
+    // rewards a rule that applied in the last iteration if that made a
+    // root cheaper
+    fn update_reward(&mut self, name: &str, improved: bool) {
+        let reward = if improved { 1.0 } else { 0.0 };
+        let stats = &mut self.stats[name];
+        stats.reward = self.decay * stats.reward + (1.0 - self.decay) * reward;
+    }
+
+    // splits the match budget between the rules that found matches in
+    // this iteration, or returns an empty split if they all fit in it.
+    // Every rule gets one match if the budget allows, and the rest is
+    // split by weight, handing out what rounding down leaves over to
+    // the largest remainders, so the shares add up to the budget.
+    fn split_budget(&self) -> IndexMap<String, usize> {
+        let total: usize = self.found.values().sum();
+        if total <= self.match_budget {
+            return IndexMap::default();
+        }
+
+        let weights: Vec<(&String, f64)> = self
+            .found
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(name, _)| (name, self.exploration + self.reward(name)))
+            .collect();
+        let total_weight: f64 = weights.iter().map(|(_, w)| w).sum();
+        let base = usize::from(self.match_budget >= weights.len());
+        let rest = self.match_budget - base * weights.len();
+
+        let mut shares: Vec<(usize, f64)> = weights
+            .iter()
+            .map(|(_, w)| {
+                let share = rest as f64 * w / total_weight;
+                (base + share.floor() as usize, share.fract())
+            })
+            .collect();
+        let given: usize = shares.iter().map(|(share, _)| share).sum();
+        let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+        by_remainder.sort_by(|&a, &b| {
+            shares[b]
+                .1
+                .partial_cmp(&shares[a].1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for &i in by_remainder
+            .iter()
+            .take(self.match_budget.saturating_sub(given))
+        {
+            shares[i].0 += 1;
+        }
+
+        weights
+            .iter()
+            .zip(shares)
+            .map(|((name, _), (share, _))| ((*name).clone(), share))
+            .collect()
+    }
+}
+
+impl<L, N, CF> RewriteScheduler<L, N> for CostScheduler<L, CF>
+where
+    L: Language,
+    N: Analysis<L>,
+    CF: CostFunction<L> + Clone,
+{
+    fn can_stop(&mut self, _iteration: usize) -> bool {
+        !self.truncated
+    }
+
+    fn search_rewrite(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+    ) -> Vec<SearchMatches> {
+        self.start_iteration(iteration, egraph);
+        let matches = rewrite.search(egraph);
+        let n: usize = matches.iter().map(|m| m.substs.len()).sum();
+        self.found.insert(rewrite.name().to_owned(), n);
+        matches
+    }
+
+    fn apply_rewrite(
+        &mut self,
+        _iteration: usize,
+        egraph: &mut EGraph<L, N>,
+        rewrite: &Rewrite<L, N>,
+        matches: Vec<SearchMatches>,
+    ) -> usize {
+        let name = rewrite.name();
+        let found = self.found.get(name).copied().unwrap_or(0);
+        if self.budgets.is_none() {
+            self.budgets = Some(self.split_budget());
+        }
+        let budgets = self.budgets.as_ref().unwrap();
+        let budget = budgets.get(name).copied().unwrap_or(usize::MAX);
+        let stats = self.stats.entry(name.to_owned()).or_default();
+
+        let matches = if found > budget {
+            debug!("Throttling {} to {} of {} matches", name, budget, found);
+            self.truncated = true;
+            let start = stats.offset % found;
+            stats.offset = start + budget;
+            take_matches(matches, start, budget)
+        } else {
             matches
+        };
+
+        let applied = rewrite.apply(egraph, &matches).len();
+        if applied > 0 {
+            self.applied.push(name.to_owned());
         }
+        applied
     }
 }
 
+// takes `limit` of the substitutions in `matches`, starting at `start`
+// and wrapping around
+fn take_matches(matches: Vec<SearchMatches>, start: usize, limit: usize) -> Vec<SearchMatches> {
+    let total: usize = matches.iter().map(|m| m.substs.len()).sum();
+    filter_matches(matches, |i| (i + total - start % total) % total < limit)
+}
+
+// keeps the substitutions whose index over all of `matches` satisfies `keep`
+fn filter_matches(
+    matches: Vec<SearchMatches>,
+    mut keep: impl FnMut(usize) -> bool,
+) -> Vec<SearchMatches> {
+    let mut i = 0;
+    let mut taken = vec![];
+    for m in matches {
+        let substs: Vec<Subst> = m
+            .substs
+            .into_iter()
+            .filter(|_| {
+                i += 1;
+                keep(i - 1)
+            })
+            .collect();
+        if !substs.is_empty() {
+            taken.push(SearchMatches {
+                eclass: m.eclass,
+                substs,
+            });
+        }
+    }
+    taken
+}
+
 /// Custom data to inject into the [`Iteration`]s recorded by a [`Runner`]
 ///
 /// This trait allows you to add custom data to the [`Iteration`]s
@@ -788,3 +2581,453 @@ where
 {
     fn make(_: &Runner<L, N, Self>) -> Self {}
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{SymbolLang as S, *};
+
+    // adds an eclass in every iteration, so it never saturates
+    fn grow() -> Vec<Rewrite<S, ()>> {
+        vec![rewrite!("grow"; "(f ?a)" => "(f (h ?a))")]
+    }
+
+    fn growing_runner() -> Runner<S, ()> {
+        Runner::default().with_expr(&"(f x)".parse().unwrap())
+    }
+
+    #[test]
+    fn stop_handle() {
+        crate::init_logger();
+        let runner = growing_runner();
+        runner.stop_handle().stop();
+        let runner = runner.run(&grow());
+        assert!(matches!(runner.stop_reason, Some(StopReason::Cancelled)));
+        assert_eq!(runner.iterations.len(), 1);
+
+        // stopping during an iteration skips the rest of it
+        let runner = growing_runner();
+        let handle = runner.stop_handle();
+        let runner = runner
+            .with_hook(move |runner| {
+                if runner.iterations.len() == 3 {
+                    handle.stop();
+                }
+                Ok(())
+            })
+            .run(&grow());
+        assert!(matches!(runner.stop_reason, Some(StopReason::Cancelled)));
+        assert_eq!(runner.iterations.len(), 4);
+        assert!(runner.iterations[3].applied.is_empty());
+    }
+
+    #[test]
+    fn run_again() {
+        crate::init_logger();
+        let rules = grow();
+        let runner = growing_runner().with_iter_limit(2).run(&rules);
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::IterationLimit(2))
+        ));
+        assert_eq!(runner.iterations.len(), 3);
+        let nodes = runner.egraph.total_size();
+
+        // the iteration limit counts the iterations of every run
+        let runner = runner.with_iter_limit(4).run(&rules);
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::IterationLimit(4))
+        ));
+        assert_eq!(runner.iterations.len(), 5);
+        assert!(runner.egraph.total_size() > nodes);
+
+        let handle = runner.stop_handle();
+        handle.stop();
+        let runner = runner.with_iter_limit(100).run(&rules);
+        assert!(matches!(runner.stop_reason, Some(StopReason::Cancelled)));
+        assert_eq!(runner.iterations.len(), 6);
+        assert!(!handle.is_stopped());
+
+        // being cancelled once doesn't cancel the next run
+        let runner = runner.with_iter_limit(7).run(&rules);
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::IterationLimit(7))
+        ));
+        assert_eq!(runner.roots.len(), 1);
+
+        // a stop that lands as the run ends for another reason is dropped too
+        let stopper = handle.clone();
+        let runner = runner
+            .with_iter_limit(100)
+            .with_hook(move |_| {
+                stopper.stop();
+                Err("done".into())
+            })
+            .run(&rules);
+        assert!(matches!(runner.stop_reason, Some(StopReason::Other(_))));
+        assert!(!handle.is_stopped());
+    }
+
+    #[test]
+    fn memory_limit() {
+        crate::init_logger();
+        let runner = growing_runner();
+        let limit = runner.egraph.estimated_memory();
+        let runner = runner
+            .with_iter_limit(100)
+            .with_memory_limit(limit)
+            .run(&grow());
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::MemoryLimit(memory)) if memory > limit
+        ));
+        assert!(runner.iterations.len() < 100);
+        assert!(runner.iterations.iter().all(|i| i.egraph_memory > 0));
+    }
+
+    #[test]
+    fn class_limit() {
+        crate::init_logger();
+        let runner = growing_runner()
+            .with_iter_limit(100)
+            .with_class_limit(5)
+            .run(&grow());
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::ClassLimit(n)) if n > 5
+        ));
+        assert!(runner.iterations.len() < 100);
+    }
+
+    #[test]
+    fn goal() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[rewrite!("comm"; "(+ ?a ?b)" => "(+ ?b ?a)")];
+        let runner = Runner::default()
+            .with_goal(&"(g a)".parse().unwrap(), &"(h a)".parse().unwrap())
+            .with_goal(&"(+ a b)".parse().unwrap(), &"(+ b a)".parse().unwrap())
+            .run(rules);
+        assert!(matches!(
+            runner.stop_reason,
+            Some(StopReason::GoalReached(1))
+        ));
+        assert!(runner.roots.is_empty());
+    }
+
+    #[test]
+    fn rule_stats() {
+        crate::init_logger();
+        let mut rules = grow();
+        rules.push(rewrite!("comm"; "(+ ?a ?b)" => "(+ ?b ?a)"));
+        let runner = Runner::default()
+            .with_expr(&"(+ (f a) (f b))".parse().unwrap())
+            .with_iter_limit(3)
+            .run(&rules);
+
+        let first = &runner.iterations[0];
+        assert_eq!(first.rules.len(), rules.len());
+        assert_eq!(first.rules["grow"].matches, 2);
+        assert_eq!(first.rules["comm"].matches, 1);
+        for (name, stats) in &first.rules {
+            assert!(stats.applied <= stats.matches);
+            assert_eq!(stats.applied, first.applied.get(name).copied().unwrap_or(0));
+            assert!(!stats.banned);
+        }
+
+        // the report sums up every iteration's stats
+        let report = runner.report();
+        assert_eq!(report.rules.len(), rules.len());
+        for (name, total) in &report.rules {
+            let stats = runner.iterations.iter().filter_map(|i| i.rules.get(name));
+            let (matches, applied) = stats.fold((0, 0), |(m, a), s| (m + s.matches, a + s.applied));
+            assert_eq!(total.matches, matches);
+            assert_eq!(total.applied, applied);
+            assert_eq!(total.banned_iterations, 0);
+        }
+    }
+
+    #[test]
+    fn run_report() {
+        crate::init_logger();
+        let runner = growing_runner().with_iter_limit(3).run(&grow());
+
+        let report = runner.report();
+        assert!(matches!(
+            report.stop_reason,
+            Some(StopReason::IterationLimit(3))
+        ));
+        assert_eq!(report.iterations.len(), runner.iterations.len());
+        assert_eq!(report.egraph_classes, runner.egraph.number_of_classes());
+        let applied: usize = runner
+            .iterations
+            .iter()
+            .map(|i| i.applied.values().sum::<usize>())
+            .sum();
+        assert_eq!(report.rules["grow"].applied, applied);
+
+        #[cfg(feature = "reports")]
+        {
+            let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+            assert_eq!(
+                json["iterations"].as_array().unwrap().len(),
+                report.iterations.len()
+            );
+            assert_eq!(json["egraph_classes"], report.egraph_classes);
+            assert_eq!(json["rules"]["grow"]["applied"], applied);
+        }
+    }
+
+    #[test]
+    fn observer() {
+        use std::{cell::RefCell, rc::Rc};
+
+        struct Events(Rc<RefCell<Vec<String>>>);
+
+        impl RunnerObserver<S, ()> for Events {
+            fn iteration_start(&mut self, iteration: usize, _egraph: &EGraph<S, ()>) {
+                self.0.borrow_mut().push(format!("start {}", iteration));
+            }
+            fn rule_applied(&mut self, _iteration: usize, rule: &str, stats: &RuleIterationStats) {
+                if stats.applied > 0 {
+                    self.0.borrow_mut().push(format!("applied {}", rule));
+                }
+            }
+            fn iteration_end(
+                &mut self,
+                iteration: usize,
+                _data: &Iteration<()>,
+                _egraph: &EGraph<S, ()>,
+            ) {
+                self.0.borrow_mut().push(format!("end {}", iteration));
+            }
+            fn stopped(&mut self, reason: &StopReason, _egraph: &EGraph<S, ()>) {
+                self.0.borrow_mut().push(format!("stopped {:?}", reason));
+            }
+        }
+
+        crate::init_logger();
+        let events = Rc::new(RefCell::new(vec![]));
+        let runner = growing_runner()
+            .with_iter_limit(2)
+            .with_observer(Events(events.clone()))
+            .run(&grow());
+
+        let events = events.borrow();
+        assert_eq!(events.first().unwrap(), "start 0");
+        assert_eq!(events.last().unwrap(), "stopped IterationLimit(2)");
+        assert!(events.contains(&"applied grow".to_owned()));
+        let ends = events.iter().filter(|e| e.starts_with("end")).count();
+        assert_eq!(ends, runner.iterations.len());
+    }
+
+    #[test]
+    fn backoff_stats() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("comm-mul"; "(* ?a ?b)" => "(* ?b ?a)"),
+        ];
+        let scheduler = BackoffScheduler::default()
+            .with_initial_match_limit(2)
+            .do_not_ban("comm-mul");
+        let runner = Runner::default()
+            .with_scheduler(scheduler)
+            .with_iter_limit(4)
+            .with_expr(&"(+ (+ a b) (+ (* c d) (* e (* f g))))".parse().unwrap())
+            .run(rules);
+
+        let scheduler = runner.scheduler_as::<BackoffScheduler>().unwrap();
+        let bans: Vec<&BanEvent> = runner.iterations.iter().flat_map(|i| &i.bans).collect();
+        assert!(!bans.is_empty());
+        for ban in &bans {
+            assert_eq!(ban.rule, "comm-add");
+            assert!(ban.matches > ban.match_limit);
+            let stats = scheduler.rule_stats(&ban.rule).unwrap();
+            assert!(stats.times_banned() > 0);
+            assert_eq!(stats.match_limit(), 2);
+        }
+        let comm_mul = scheduler.rule_stats("comm-mul").unwrap();
+        assert_eq!(comm_mul.times_banned(), 0);
+        assert_eq!(comm_mul.match_limit(), usize::MAX);
+    }
+
+    #[test]
+    fn group_scheduler() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[
+            rewrite!("zero-add"; "(+ ?a 0)" => "?a"),
+            rewrite!("zero-mul"; "(* ?a 0)" => "0"),
+            rewrite!("one-mul"; "(* ?a 1)" => "?a"),
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("comm-mul"; "(* ?a ?b)" => "(* ?b ?a)"),
+            rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+            rewrite!("assoc-mul"; "(* ?a (* ?b ?c))" => "(* (* ?a ?b) ?c)"),
+        ];
+        let cheap = ["zero-add", "zero-mul", "one-mul"];
+        let expensive = ["comm-add", "comm-mul", "assoc-add", "assoc-mul"];
+
+        let scheduler = GroupScheduler::default()
+            .with_group("cheap", &cheap, SimpleScheduler)
+            .with_group("expensive", &expensive, BackoffScheduler::default())
+            .then_saturate("cheap")
+            .then_run("expensive", 1);
+        let runner = Runner::default()
+            .with_scheduler(scheduler)
+            .with_expr(&"(+ (* x 1) (+ (* y 0) (+ z w)))".parse().unwrap())
+            .run(rules);
+
+        assert!(matches!(runner.stop_reason, Some(StopReason::Saturated)));
+        for iteration in &runner.iterations {
+            let cheap_applied = iteration
+                .applied
+                .keys()
+                .any(|r| cheap.contains(&r.as_str()));
+            let expensive_applied = iteration
+                .applied
+                .keys()
+                .any(|r| expensive.contains(&r.as_str()));
+            assert!(!(cheap_applied && expensive_applied));
+        }
+        let first = &runner.iterations[0].applied;
+        assert!(first.contains_key("one-mul") && first.contains_key("zero-mul"));
+
+        let scheduler = runner.scheduler_as::<GroupScheduler<_, _>>().unwrap();
+        let expensive = scheduler.group_as::<BackoffScheduler>("expensive").unwrap();
+        assert!(expensive.rule_stats("comm-add").is_some());
+        assert!(scheduler.group_as::<BackoffScheduler>("cheap").is_none());
+    }
+
+    #[test]
+    fn cost_scheduler_rewards() {
+        crate::init_logger();
+        let useful: Rewrite<S, ()> = rewrite!("zero-add"; "(+ ?a 0)" => "?a");
+        let useless = rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)");
+        let rules = [&useless, &useful];
+
+        let mut egraph = EGraph::default();
+        let root = egraph.add_expr(&"(+ (+ x 0) (+ y 0))".parse().unwrap());
+        egraph.rebuild();
+        let mut scheduler = CostScheduler::new(AstSize, &[root]);
+
+        // a rule shares the credit for its whole iteration, so the rules take
+        // turns; the last search rewards the last iteration
+        for i in 0..4 {
+            let rule = rules[i % 2];
+            let matches = scheduler.search_rewrite(i, &egraph, rule);
+            if i < 3 {
+                scheduler.apply_rewrite(i, &mut egraph, rule, matches);
+                egraph.rebuild();
+            }
+        }
+
+        assert!(scheduler.reward("zero-add") > 0.0);
+        assert_eq!(scheduler.reward("comm-add"), 0.0);
+    }
+
+    #[test]
+    fn match_limit_policy() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("comm-mul"; "(* ?a ?b)" => "(* ?b ?a)"),
+            rewrite!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+        ];
+        let run = |policy| {
+            let scheduler = BackoffScheduler::default()
+                .with_initial_match_limit(5)
+                // the default policy also applies to rules configured before it
+                .rule_ban_length("comm-add", 2)
+                .with_match_limit_policy(policy);
+            let runner = Runner::default()
+                .with_scheduler(scheduler)
+                .with_iter_limit(5)
+                .with_incremental_search(true)
+                .with_expr(&"(+ (* a b) (+ (* c d) (+ e (* f g))))".parse().unwrap())
+                .run(rules);
+            let applied: Vec<_> = runner
+                .iterations
+                .iter()
+                .map(|i| i.applied.clone())
+                .collect();
+            (runner, applied)
+        };
+
+        // the rules that found too many matches, with how they were limited
+        let limited = |runner: &Runner<S, ()>| -> Vec<(String, MatchLimitPolicy)> {
+            let rules = runner.iterations.iter().flat_map(|i| &i.rules);
+            rules
+                .filter(|(_, stats)| stats.dropped > 0)
+                .map(|(name, stats)| {
+                    assert_eq!(stats.matches, 5);
+                    (name.clone(), stats.limit_policy.unwrap())
+                })
+                .collect()
+        };
+
+        let (truncated, _) = run(MatchLimitPolicy::Truncate);
+        assert!(truncated.iterations.iter().all(|i| i.bans.is_empty()));
+        for iteration in &truncated.iterations {
+            assert!(iteration.applied.values().all(|&n| n <= 5));
+        }
+        let limited_rules = limited(&truncated);
+        assert!(limited_rules.contains(&("comm-add".to_owned(), MatchLimitPolicy::Truncate)));
+        assert!(limited_rules
+            .iter()
+            .all(|(_, policy)| *policy == MatchLimitPolicy::Truncate));
+
+        let (sampled, applied) = run(MatchLimitPolicy::Sample(7));
+        assert!(sampled.iterations.iter().all(|i| i.bans.is_empty()));
+        assert!(limited(&sampled)
+            .iter()
+            .all(|(_, policy)| *policy == MatchLimitPolicy::Sample(7)));
+        let (_, applied_again) = run(MatchLimitPolicy::Sample(7));
+        assert_eq!(applied, applied_again);
+    }
+
+    #[test]
+    fn match_limit_policy_progress() {
+        crate::init_logger();
+        let rules: &[Rewrite<S, ()>] = &[
+            rewrite!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+            rewrite!("comm-mul"; "(* ?a ?b)" => "(* ?b ?a)"),
+        ];
+        let expr = "(+ (* a b) (+ (* c d) (+ e (* f g))))".parse().unwrap();
+        let run = |policy| {
+            let scheduler = BackoffScheduler::default()
+                .with_initial_match_limit(1)
+                .with_match_limit_policy(policy);
+            Runner::default()
+                .with_scheduler(scheduler)
+                .with_iter_limit(30)
+                .with_expr(&expr)
+                .run(rules)
+        };
+        let saturated = Runner::default().with_expr(&expr).run(rules);
+        assert!(matches!(saturated.stop_reason, Some(StopReason::Saturated)));
+
+        // the rules always find more matches than the limit, so the runner
+        // never saturates, but truncating still reaches every match
+        for policy in [MatchLimitPolicy::Truncate, MatchLimitPolicy::Sample(7)] {
+            let runner = run(policy);
+            assert!(matches!(
+                runner.stop_reason,
+                Some(StopReason::IterationLimit(30))
+            ));
+            for iteration in &runner.iterations {
+                if iteration.rules.values().any(|stats| stats.dropped > 0) {
+                    assert!(!matches!(
+                        iteration.stop_reason,
+                        Some(StopReason::Saturated)
+                    ));
+                }
+            }
+            if policy == MatchLimitPolicy::Truncate {
+                assert_eq!(
+                    runner.egraph.total_number_of_nodes(),
+                    saturated.egraph.total_number_of_nodes()
+                );
+            }
+        }
+    }
+}
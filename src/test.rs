@@ -54,17 +54,7 @@ pub fn test_runner<L, A>(
     }
 
     if check_fn.is_none() {
-        let goals = goals.to_vec();
-        runner = runner.with_hook(move |r| {
-            if goals
-                .iter()
-                .all(|g: &Pattern<_>| g.search_eclass(&r.egraph, id).is_some())
-            {
-                Err("Done".into())
-            } else {
-                Ok(())
-            }
-        });
+        runner = runner.with_goal_patterns(id, goals.to_vec());
     }
     let runner = runner.run(rules);
 
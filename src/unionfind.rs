@@ -2,6 +2,7 @@ use crate::Id;
 use std::fmt::Debug;
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnionFind {
     parents: Vec<Id>,
 }
@@ -13,6 +14,10 @@ impl UnionFind {
         id
     }
 
+    pub fn size(&self) -> usize {
+        self.parents.len()
+    }
+
     fn parent(&self, query: Id) -> Id {
         self.parents[usize::from(query)]
     }
@@ -37,6 +42,43 @@ impl UnionFind {
         current
     }
 
+    /// Makes `root` its own leader again, undoing a union.
+    pub fn make_root(&mut self, root: Id) {
+        *self.parent_mut(root) = root;
+    }
+
+    /// Removes the sets made after the first `size`.
+    /// None of the remaining ids may point to them.
+    pub fn truncate(&mut self, size: usize) {
+        self.parents.truncate(size);
+        debug_assert!(self.parents.iter().all(|&p| usize::from(p) < size));
+    }
+
+    /// Checks that every parent is in bounds and that following parents
+    /// always reaches a root, for a union-find that was deserialized.
+    #[cfg(feature = "serde-1")]
+    pub fn check(&self) -> Result<(), String> {
+        let n = self.parents.len();
+        if let Some(p) = self.parents.iter().find(|&&p| usize::from(p) >= n) {
+            return Err(format!("Id {} is out of bounds, there are {} ids", p, n));
+        }
+        // ids known to reach a root
+        let mut done = vec![false; n];
+        for start in 0..n {
+            let mut path = vec![];
+            let mut current = start;
+            while !done[current] && self.parents[current] != Id::from(current) {
+                if path.len() > n {
+                    return Err(format!("Id {} is in a cycle", start));
+                }
+                path.push(current);
+                current = self.parents[current].into();
+            }
+            path.into_iter().for_each(|id| done[id] = true);
+        }
+        Ok(())
+    }
+
     /// Returns (new_leader, old_leader)
     pub fn union(&mut self, root1: Id, root2: Id) -> Id {
         assert_eq!(root1, self.parent(root1));
@@ -26,6 +26,39 @@ pub(crate) fn concat_vecs<T>(to: &mut Vec<T>, mut from: Vec<T>) {
     to.extend(from);
 }
 
+// A small, seedable pseudorandom number generator (SplitMix64), so
+// that sampling is deterministic without pulling in `rand`.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Returns `k` distinct indices below `n` in sorted order,
+    // or all of them if `k >= n`.
+    pub(crate) fn sample(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..n).collect();
+        let k = k.min(n);
+        for i in 0..k {
+            let j = i + (self.next_u64() % (n - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices.truncate(k);
+        indices.sort_unstable();
+        indices
+    }
+}
+
 static STRINGS: Lazy<Mutex<IndexSet<&'static str>>> = Lazy::new(Default::default);
 
 /// An interned string.
@@ -111,6 +144,56 @@ impl fmt::Debug for Symbol {
     }
 }
 
+#[cfg(feature = "serde-1")]
+impl serde::Serialize for Symbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde-1")]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str> as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(Symbol::from(s))
+    }
+}
+
+/// Serializes a map as a sequence of key-value pairs, so keys don't have
+/// to be strings. Use with `#[serde(with = "vectorize")]`.
+#[cfg(feature = "serde-1")]
+pub(crate) mod vectorize {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::iter::FromIterator;
+
+    pub fn serialize<'a, T, K, V, S>(target: T, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: IntoIterator<Item = (&'a K, &'a V)>,
+        K: Serialize + 'a,
+        V: Serialize + 'a,
+    {
+        ser.collect_seq(target)
+    }
+
+    pub fn deserialize<'de, T, K, V, D>(des: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromIterator<(K, V)>,
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        let pairs = Vec::<(K, V)>::deserialize(des)?;
+        Ok(T::from_iter(pairs))
+    }
+}
+
 /// A wrapper that uses display implementation as debug
 pub(crate) struct DisplayAsDebug<T>(pub T);
 
@@ -86,7 +86,7 @@ impl Analysis<Math> for ConstantFold {
             let added = egraph.add(Math::Constant(c));
             let (id, _did_something) = egraph.union(id, added);
             // to not prune, comment this out
-            egraph[id].nodes.retain(|n| n.is_leaf());
+            egraph.prune_class(id, |n| n.is_leaf());
 
             assert!(
                 !egraph[id].nodes.is_empty(),
@@ -218,6 +218,21 @@ egg::test_fn! {
     @check |r: Runner<Math, ()>| assert_eq!(r.egraph.number_of_classes(), 127)
 }
 
+egg::test_fn! {
+    math_associate_adds_incremental, [
+        rw!("comm-add"; "(+ ?a ?b)" => "(+ ?b ?a)"),
+        rw!("assoc-add"; "(+ ?a (+ ?b ?c))" => "(+ (+ ?a ?b) ?c)"),
+    ],
+    runner = Runner::default()
+        .with_iter_limit(7)
+        .with_scheduler(SimpleScheduler)
+        .with_incremental_search(true),
+    "(+ 1 (+ 2 (+ 3 (+ 4 (+ 5 (+ 6 7))))))"
+    =>
+    "(+ 7 (+ 6 (+ 5 (+ 4 (+ 3 (+ 2 1))))))"
+    @check |r: Runner<Math, ()>| assert_eq!(r.egraph.number_of_classes(), 127)
+}
+
 egg::test_fn! {
     #[should_panic(expected = "Could not prove goal 0")]
     math_fail, rules(),
@@ -300,3 +315,66 @@ egg::test_fn! {
 egg::test_fn! {
     integ_part3, rules(), "(i (ln x) x)" => "(- (* x (ln x)) x)"
 }
+
+egg::test_fn! {
+    math_cost_scheduler, rules(),
+    runner = {
+        let runner: Runner<Math, ConstantFold> = Runner::default()
+            .with_expr(&"(+ (* x (+ 0 1)) (* (+ y 0) (- z z)))".parse().unwrap());
+        let scheduler = CostScheduler::new(AstSize, &runner.roots).with_match_budget(50);
+        runner.with_scheduler(scheduler).with_iter_limit(8)
+    },
+    "(+ (* x (+ 0 1)) (* (+ y 0) (- z z)))" => "x"
+    @check |r: Runner<Math, ConstantFold>| {
+        for iteration in &r.iterations {
+            assert!(iteration.applied.values().sum::<usize>() <= 50);
+        }
+        let (cost, best) = Extractor::new(&r.egraph, AstSize).find_best(r.roots[0]);
+        assert_eq!(cost, 1, "{}", best);
+    }
+}
+
+egg::test_fn! {
+    math_push_pop, rules(),
+    runner = Runner::default().with_iter_limit(3),
+    "(+ (* x x) (* (* y y) 2))" => "(+ (* x x) (* 2 (* y y)))"
+    @check |r: Runner<Math, ConstantFold>| {
+        let mut egraph = r.egraph;
+        let root = r.roots[0];
+        let xx = egraph.add_expr(&"(* x x)".parse().unwrap());
+        let yy = egraph.add_expr(&"(* y y)".parse().unwrap());
+        let snapshot = |egraph: &EGraph| {
+            let mut classes: Vec<_> = egraph
+                .classes()
+                .map(|c| (c.id, egraph.find(c.id), c.nodes.clone(), c.data))
+                .collect();
+            classes.sort_by_key(|c| c.0);
+            classes
+        };
+        let before = snapshot(&egraph);
+        let n_classes = egraph.number_of_classes();
+
+        // assume x*x = 9 and y*y = 1, so the root folds to 11 and gets pruned
+        egraph.push();
+        let nine = egraph.add_expr(&"9".parse().unwrap());
+        let one = egraph.add_expr(&"1".parse().unwrap());
+        egraph.union(xx, nine);
+        egraph.union(yy, one);
+        egraph.rebuild();
+        assert_eq!(egraph[root].data, Some(11.0.into()));
+        assert!(egraph[root].nodes.iter().all(|n| n.is_leaf()));
+        assert_eq!(egraph.find(xx), egraph.find(nine));
+
+        egraph.pop();
+        assert_eq!(egraph.number_of_classes(), n_classes);
+        assert_eq!(snapshot(&egraph), before);
+        assert_eq!(egraph[xx].data, None);
+
+        // the egraph can still be rewritten
+        let runner: Runner<Math, ConstantFold> = Runner::default()
+            .with_egraph(egraph)
+            .with_iter_limit(3)
+            .run(&rules());
+        assert!(runner.egraph[root].data.is_none());
+    }
+}